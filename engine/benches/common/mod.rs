@@ -0,0 +1,31 @@
+use engine::{
+    environment::Environment,
+    pc::{History, Map},
+    piece::Queue,
+    randomizer::{Builtin, Rng},
+};
+
+// the same setup the tests use
+#[path = "../../tests/common/mod.rs"]
+mod fixture;
+pub use fixture::state;
+
+/// Every benchmark deals its queues from this seed, so runs can be compared.
+pub const SEED: i32 = 0x3e5;
+
+/// `count` 7-bag queues of `len` pieces, each from its own game.
+#[must_use]
+pub fn queues(count: usize, len: usize) -> Vec<Queue> {
+    let mut seeds = Rng::new(SEED);
+    (0..count)
+        .map(|_| Rng::pieces(seeds.next(), Builtin::Bag7).take(len).collect())
+        .collect()
+}
+
+/// The checked-in n=6 table for `ftdhu`, read without writing a `.pcb` next to it.
+#[allow(dead_code)]
+#[must_use]
+pub fn pcs_6() -> Map<Queue, History> {
+    let text = std::fs::read_to_string("data/srsx_ftdhu_handheld_6.pc").unwrap();
+    Environment::parse_pcs(&text).unwrap()
+}
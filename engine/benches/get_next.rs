@@ -1,94 +1,47 @@
-use std::collections::HashMap;
+use std::hint::black_box;
 
 use criterion::{Criterion, criterion_group, criterion_main};
-use engine::{
-    environment::{DropType, Environment},
-    file::{corners::Corners, kicks::Kicks, piece::Bag},
-    repl::State,
-};
+use engine::{board::Board, environment::Environment};
 
-pub fn get_next_bm(c: &mut Criterion) {
-    // c.bench_function("get_pos", |b| {
-    //     let mut r = Rng::new(0);
-    //     b.iter_batched(
-    //         || {
-    //             let lo: u128 =
-    //                 unsafe { std::mem::transmute([r.next(), r.next(), r.next(), r.next()]) };
-    //             let hi: u128 =
-    //                 unsafe { std::mem::transmute([r.next(), r.next(), r.next(), r.next()]) };
-
-    //             let x = r.next();
-    //             let y = r.next();
-    //             (Board { lo, hi }, ((x % 4) as usize, (y % 64) as usize))
-    //         },
-    //         |(b, (x, y))| black_box(b.get(x % 4, y % 64)),
-    //         criterion::BatchSize::SmallInput,
-    //     );
-    // });
-    c.bench_function("pc_gen", |_b| {
-        let k_file = std::fs::read_to_string("data/srsx.kick").unwrap();
-        let b_file = std::fs::read_to_string("data/tetromino.piece").unwrap();
-        let c_file = std::fs::read_to_string("data/handheld.corners").unwrap();
-
-        let kicks: Kicks = k_file.parse().unwrap();
-        let bag: Bag = b_file.parse().unwrap();
-        let corners: Corners = c_file.parse().unwrap();
-        let env: Environment = Environment {
-            droptype: DropType::Sonic,
-            vision: 7,
-            foresight: 1,
-            can_180: true,
-            can_das: true,
-            can_tap: true,
-            can_hold: true,
-            upstack: true,
-            state: &mut State {
-                kicks,
-                bag,
-                corners,
-                fingerprint: ("srsx".into(), "tetromino".into(), "handheld".into()),
-                pcs: HashMap::new(),
-            },
-        };
+mod common;
 
-        let _ = env;
+/// Boards the bot actually sees in the 4-wide well.
+const BOARDS: &[(&str, &str)] = &[
+    ("empty", ""),
+    ("3-residual", "X___|XX__"),
+    ("3-residual-split", "X__X|X___"),
+    ("pc-half", "XX__|XXX_"),
+    ("tsd-slot", "X___|X_XX"),
+    ("tall", "X___|XX__|XXX_|XX_X|X_XX"),
+];
 
-        // b.iter(|_| generate_all_pc_queues(4, &env));
+pub fn get_next_bm(c: &mut Criterion) {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 7, 0);
+    let pieces: Vec<u8> = common::queues(1, 56)[0].into_iter().collect();
+
+    let mut group = c.benchmark_group("get_next_boards");
+    for &(name, board) in BOARDS {
+        let board: Board = board.parse().unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for &p in &pieces {
+                    black_box(black_box(board).get_next_boards(p, &env));
+                }
+            });
+        });
+    }
+
+    // the 10-wide matrix TBP front-ends send
+    let wide: Board<10> = "XXX____XXX|XXX____XXX|XXXX___XXX".parse().unwrap();
+    group.bench_function("10-wide", |b| {
+        b.iter(|| {
+            for &p in &pieces {
+                black_box(black_box(wide).get_next_boards(p, &env));
+            }
+        });
     });
-    // c.bench_function("get_next", |b| {
-    //     let mut r = Rng::new(0);
-    //     let k_file = std::fs::read_to_string("data/srsx.kick").unwrap();
-    //     let b_file = std::fs::read_to_string("data/tetromino.piece").unwrap();
-    //     let c_file = std::fs::read_to_string("data/handheld.corners").unwrap();
-
-    //     let kicks: Kicks = k_file.parse().unwrap();
-    //     let bag: Bag = b_file.parse().unwrap();
-    //     let corners: Corners = c_file.parse().unwrap();
-    //     let env: Environment = Environment {
-    //         bag,
-    //         kicks,
-    //         corners,
-    //         droptype: DropType::Sonic,
-    //         vision: 7,
-    //         foresight: 1,
-    //         can_180: true,
-    //         can_das: true,
-    //         can_tap: true,
-    //         can_hold: true,
-    //         upstack: true,
-    //     };
-
-    //     b.iter_batched(
-    //         move || {
-    //             (
-    //                 *r.pick(&['T', 'I', 'L', 'J', 'O', 'S', 'Z']),
-    //                 Board::from_str("XX_X").unwrap(),
-    //             )
-    //         },
-    //         |i| black_box(i.1.get_next_boards(black_box(i.0), black_box(&env))),
-    //         criterion::BatchSize::SmallInput,
-    //     );
-    // });
+    group.finish();
 }
 
 criterion_group!(benches, get_next_bm);
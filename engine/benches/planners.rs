@@ -0,0 +1,71 @@
+use std::{hint::black_box, io, time::Duration};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use engine::{
+    board::Board,
+    environment::Environment,
+    pc::{generate_all_pc_queues, max_pcs_in_queue},
+    ren::best_ren,
+};
+
+mod common;
+
+pub fn pc_generation_bm(c: &mut Criterion) {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+
+    let mut group = c.benchmark_group("generate_all_pc_queues");
+    group.sample_size(10);
+    group.bench_function("n=4", |b| {
+        b.iter(|| generate_all_pc_queues::<4>(&mut io::sink(), black_box(4), &env));
+    });
+
+    // one iteration takes a few seconds
+    group.measurement_time(Duration::from_secs(60));
+    group.bench_function("n=5", |b| {
+        b.iter(|| generate_all_pc_queues::<4>(&mut io::sink(), black_box(5), &env));
+    });
+    group.finish();
+}
+
+pub fn max_pcs_bm(c: &mut Criterion) {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+    let pcs = common::pcs_6();
+    let queues = common::queues(16, 14);
+
+    c.bench_function("max_pcs_in_queue", |b| {
+        b.iter(|| {
+            for &q in &queues {
+                black_box(max_pcs_in_queue(None, black_box(q), &env, &pcs));
+            }
+        });
+    });
+}
+
+pub fn ren_bm(c: &mut Criterion) {
+    let board: Board = "X___|XX__".parse().unwrap();
+
+    let mut group = c.benchmark_group("best_ren");
+    group.sample_size(10);
+    for vision in [6, 14] {
+        let mut state = common::state();
+        let env = Environment::new(&mut state, "ftdhu", vision, 0);
+        let queues: Vec<Vec<u8>> = common::queues(16, vision)
+            .into_iter()
+            .map(|q| q.into_iter().collect())
+            .collect();
+
+        group.bench_function(format!("vision={vision}"), |b| {
+            b.iter(|| {
+                for q in &queues {
+                    black_box(best_ren(board, None, q, &env));
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, pc_generation_bm, max_pcs_bm, ren_bm);
+criterion_main!(benches);
@@ -1,278 +1,225 @@
-/// 4x64 board.
+/// Number of rows a [`Board`] can hold.
+pub const HEIGHT: usize = 64;
+
+/// `W`x64 board.
+///
+/// Stored column-major: bit `y` of `cols[x]` is the cell at `(x, y)`, with row 0 at the bottom.
+/// Defaults to the 4-wide field the engine was built around.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub struct Board {
-    pub lo: u128,
-    pub hi: u128,
+pub struct Board<const W: usize = 4> {
+    pub cols: [u64; W],
 }
 
-impl Board {
+impl<const W: usize> Board<W> {
     #[must_use]
     pub fn empty() -> Self {
-        Self { lo: 0, hi: 0 }
+        Self { cols: [0; W] }
     }
 
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.lo == 0 && self.hi == 0
+        self.cols.iter().all(|&c| c == 0)
     }
 
     #[inline]
     #[must_use]
     pub fn get(&self, x: usize, y: usize) -> bool {
-        if x >= 4 || y >= 64 {
+        if x >= W || y >= HEIGHT {
             return false;
         }
-        if y < 32 {
-            // bottom half
-            (self.lo >> (y * 4 + x)) & 1 != 0
-        } else {
-            // top half
-            (self.hi >> ((y - 32) * 4 + x)) & 1 != 0
-        }
+
+        (self.cols[x] >> y) & 1 != 0
     }
 
     #[inline]
     pub fn set(&mut self, x: usize, y: usize, value: bool) {
-        if x >= 4 || y >= 64 {
+        if x >= W || y >= HEIGHT {
             return;
         }
 
-        let bit_index = (y % 32) * 4 + x;
-        if y < 32 {
-            if value {
-                self.lo |= 1 << bit_index;
-            } else {
-                self.lo &= !(1 << bit_index);
-            }
-        } else if value {
-            self.hi |= 1 << bit_index;
+        if value {
+            self.cols[x] |= 1 << y;
         } else {
-            self.hi &= !(1 << bit_index);
+            self.cols[x] &= !(1 << y);
         }
     }
 
     #[inline]
     #[must_use]
     pub fn num_minos(&self) -> u32 {
-        self.lo.count_ones() + self.hi.count_ones()
+        self.cols.iter().map(|c| c.count_ones()).sum()
     }
 
-    pub fn skim(&mut self) {
-        let mut rows = [0u8; 64];
+    /// Mask of the rows that are completely filled.
+    #[inline]
+    #[must_use]
+    pub fn full_rows(&self) -> u64 {
+        self.cols.iter().fold(u64::MAX, |a, &c| a & c)
+    }
 
-        // extract rows: bottom 32 -> lo, top 32 -> hi
-        for y in 0..32 {
-            rows[y] = ((self.lo >> (y * 4)) & 0b1111) as u8; // bottom half
-            rows[32 + y] = ((self.hi >> (y * 4)) & 0b1111) as u8; // top half
-        }
+    /// Mask of the rows that have at least one filled cell.
+    #[inline]
+    #[must_use]
+    fn occupied_rows(&self) -> u64 {
+        self.cols.iter().fold(0, |a, &c| a | c)
+    }
 
-        let mut new_rows = [0u8; 64];
-        let mut write = 0; // start writing from the bottom
-        for item in rows {
-            if item != 0b1111 {
-                new_rows[write] = item;
-                write += 1;
+    pub fn skim(&mut self) {
+        let mut full = self.full_rows();
+
+        // clear from the top down so lower row indices stay valid
+        while full != 0 {
+            let y = 63 - full.leading_zeros() as usize;
+            let below = (1u64 << y) - 1;
+            for c in &mut self.cols {
+                *c = (*c & below) | ((*c >> 1) & !below);
             }
-        }
-
-        // rebuild lo and hi from new_rows
-        self.lo = 0;
-        self.hi = 0;
-        for y in 0..32 {
-            self.lo |= u128::from(new_rows[y]) << (y * 4);
-            self.hi |= u128::from(new_rows[32 + y]) << (y * 4);
+            full &= !(1 << y);
         }
     }
+
     #[must_use]
     pub const fn width(&self) -> usize {
-        4
+        W
     }
 
     #[must_use]
     pub fn height(&self) -> usize {
-        // scan top half first
-        for row in (0..32).rev() {
-            if (self.hi >> (row * 4)) & 0b1111 != 0 {
-                return row + 1 + 32; // row index + 1, counting rows
-            }
-        }
-
-        // then bottom half
-        for row in (0..32).rev() {
-            if (self.lo >> (row * 4)) & 0b1111 != 0 {
-                return row + 1; // row index + 1
-            }
-        }
+        HEIGHT - self.occupied_rows().leading_zeros() as usize
+    }
 
-        0 // completely empty
+    fn row_str(&self, y: usize) -> String {
+        (0..W)
+            .map(|x| if self.get(x, y) { 'X' } else { '_' })
+            .collect()
     }
 
     #[must_use]
     pub fn small(&self) -> String {
-        use std::fmt::Write;
-        let mut seen = false;
-
-        let mut v = vec![];
-        let write_half = move |half: u128, seen: &mut bool, v: &mut Vec<String>| {
-            for row in (0..32).rev() {
-                let empty = (half >> (row * 4)) & 0b1111;
-                if empty != 0 || *seen {
-                    *seen = true;
-                    let mut z = String::new();
-                    for col in 0..4 {
-                        let bit = (half >> (row * 4 + col)) & 1;
-                        write!(z, "{}", if bit == 1 { 'X' } else { '_' }).unwrap();
-                    }
-
-                    v.push(z);
-                }
-            }
-        };
-
-        write_half(self.hi, &mut seen, &mut v);
-        write_half(self.lo, &mut seen, &mut v);
-        v.join("|")
+        (0..self.height())
+            .rev()
+            .map(|y| self.row_str(y))
+            .collect::<Vec<_>>()
+            .join("|")
     }
 
+    /// Every distinct board reachable by placing `piece`, with the shortest inputs found for it.
+    ///
+    /// Breadth-first over piece positions; each state is its parent's [`Input`] plus one key, so nothing is replayed from spawn.
+    /// Paths stop growing at [`Finesse::MAX_LEN`] keys.
+    ///
+    /// The order is fixed: fewest inputs first, then by the first key that differs, in [`Environment::keyboard`] order.
+    /// A board reachable several ways keeps the first of them, so its finesse is the least under that same order.
     #[must_use]
-    pub fn get_next_boards(&self, piece: u8, environment: &Environment) -> Vec<(Self, Finesse)> {
+    pub fn get_next_boards(
+        &self,
+        piece: u8,
+        environment: &Environment,
+    ) -> Vec<(Placement<W>, Finesse)> {
         let mut queue = VecDeque::new();
-        let mut visited_active = HashMap::new();
-        let mut final_placements = HashMap::new();
+        let mut visited_active = FxHashSet::default();
+        // resting positions already locked; a later path to the same one can't come first
+        let mut landed = FxHashSet::default();
+        let mut boards = FxHashSet::default();
+        let mut final_placements = vec![];
 
         let available_keys = environment.keyboard();
 
         // (not placed)
-        let initial_state = {
-            let i = Input::new(*self, piece, environment);
-            i.fingerprint()
-        };
-
-        let initial_finesse = Finesse::new();
-        queue.push_back(initial_finesse);
-        visited_active.insert(initial_state, initial_finesse);
-
-        while let Some(input_seq) = queue.pop_front() {
-            // if this sequence leads to a new final placement
-            let placed_state = {
-                let mut i = Input::new(*self, piece, environment);
-                i.apply(input_seq);
-                i.place(true)
-            };
-            final_placements.entry(placed_state).or_insert(input_seq);
-
-            for key in &available_keys {
-                let mut new_seq = input_seq;
-                new_seq.push(*key);
-
-                let next_active_state = {
-                    let mut i = Input::new(*self, piece, environment);
-                    i.apply(new_seq);
-                    i.fingerprint()
-                };
-
-                if visited_active.contains_key(&next_active_state) {
-                    continue;
+        let initial_state = Input::<W>::new(*self, piece, environment);
+        visited_active.insert(initial_state.fingerprint());
+        queue.push_back((initial_state, Finesse::new()));
+
+        // states come off the queue in the output order, so placements only need to be kept as they're found
+        while let Some((state, input_seq)) = queue.pop_front() {
+            // if this sequence leads to a new final placement; a piece that doesn't fit where it
+            // spawned can still move, but not lock
+            let mut dropped = state;
+            dropped.sonic_drop();
+            if dropped.is_valid() && landed.insert(dropped.fingerprint()) {
+                let placed_state = dropped.place(false);
+                if boards.insert(placed_state.board) {
+                    final_placements.push((placed_state, input_seq.with_spin(placed_state.spin)));
                 }
+            }
+
+            if input_seq.len >= Finesse::MAX_LEN {
+                continue;
+            }
+
+            for &key in &available_keys {
+                let mut next_state = state;
+                next_state.send(key);
 
-                visited_active.insert(next_active_state, new_seq);
-                queue.push_back(new_seq);
+                if visited_active.insert(next_state.fingerprint()) {
+                    let mut new_seq = input_seq;
+                    new_seq.push(key);
+                    queue.push_back((next_state, new_seq));
+                }
             }
         }
 
-        final_placements.into_iter().collect()
+        final_placements
     }
 }
-impl std::fmt::Display for Board {
+
+impl<const W: usize> std::fmt::Display for Board<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.lo == 0 && self.hi == 0 {
+        if self.is_empty() {
             writeln!(f, "<empty>")?;
             return Ok(());
         }
-        let mut seen = false;
-
-        let write_half =
-            |half: u128, f: &mut std::fmt::Formatter<'_>, seen: &mut bool| -> std::fmt::Result {
-                for row in (0..32).rev() {
-                    let empty = (half >> (row * 4)) & 0b1111;
-                    if empty != 0 || *seen {
-                        *seen = true;
-                        write!(f, "|")?;
-                        for col in 0..4 {
-                            let bit = (half >> (row * 4 + col)) & 1;
-                            write!(f, "{}", if bit == 1 { 'X' } else { '_' })?;
-                        }
-                        writeln!(f, "|")?;
-                    }
-                }
-                Ok(())
-            };
 
-        write_half(self.hi, f, &mut seen)?;
-        write_half(self.lo, f, &mut seen)?;
+        for y in (0..self.height()).rev() {
+            writeln!(f, "|{}|", self.row_str(y))?;
+        }
         Ok(())
     }
 }
 
-impl FromStr for Board {
+impl<const W: usize> FromStr for Board<W> {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut hi: u128 = 0;
-        let mut lo: u128 = 0;
+        let mut board = Self::empty();
 
         let rows: Vec<&str> = s.split('|').filter(|r| !r.is_empty()).collect();
-        if rows.len() > 64 {
+        if rows.len() > HEIGHT {
             return Err("too many rows".to_string());
         }
 
-        let (hi_rows, lo_rows) = if rows.len() <= 32 {
-            (Vec::new(), rows)
-        } else {
-            let split = rows.len() - 32;
-            (rows[..split].to_vec(), rows[split..].to_vec())
-        };
-        let encode = |rows: &[&str], target: &mut u128| -> Result<(), String> {
-            for (i, &line) in rows.iter().rev().enumerate() {
-                if line.len() != 4 {
-                    return Err(format!("invalid row: {line}"));
-                }
-                let row = i; // row 0 = bottom of half
-                for (col, ch) in line.chars().enumerate() {
-                    match ch {
-                        'X' => *target |= 1 << (row * 4 + col),
-                        '_' => {}
-                        _ => return Err(format!("invalid char: {ch}")),
-                    }
+        // row 0 = bottom
+        for (y, &line) in rows.iter().rev().enumerate() {
+            if line.len() != W {
+                return Err(format!("invalid row: {line}"));
+            }
+            for (x, ch) in line.chars().enumerate() {
+                match ch {
+                    'X' => board.set(x, y, true),
+                    '_' => {}
+                    _ => return Err(format!("invalid char: {ch}")),
                 }
             }
-            Ok(())
-        };
-        encode(&hi_rows, &mut hi)?;
-        encode(&lo_rows, &mut lo)?;
+        }
 
-        Ok(Board { lo, hi })
+        Ok(board)
     }
 }
-impl std::fmt::Debug for Board {
+impl<const W: usize> std::fmt::Debug for Board<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if f.alternate() {
             write!(f, "{self:#}")
         } else {
-            f.debug_tuple("Board")
-                .field(&self.lo)
-                .field(&self.hi)
-                .finish()
+            f.debug_tuple("Board").field(&self.cols).finish()
         }
     }
 }
 
-use std::{
-    collections::{HashMap, VecDeque},
-    str::FromStr,
-};
+use std::{collections::VecDeque, str::FromStr};
+
+use rustc_hash::FxHashSet;
 
 use crate::{
     environment::Environment,
-    input::{Finesse, Input},
+    input::{Finesse, Input, Placement},
 };
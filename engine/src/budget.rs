@@ -0,0 +1,68 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// How long searches may run before settling for the best they've found so far.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    /// Time allowed from each [`Budget::start`]; `None` for no limit.
+    pub time: Option<Duration>,
+    deadline: Option<Instant>,
+    /// Searches stop once this is cleared.
+    running: Option<Arc<AtomicBool>>,
+}
+
+impl Budget {
+    #[must_use]
+    pub fn new(time: Option<Duration>) -> Self {
+        Self {
+            time,
+            ..Self::default()
+        }
+    }
+
+    /// Also stops searches once `running` is cleared.
+    #[must_use]
+    pub fn watch(self, running: Arc<AtomicBool>) -> Self {
+        Self {
+            running: Some(running),
+            ..self
+        }
+    }
+
+    /// Starts the clock; searches have `time` from now.
+    pub fn start(&mut self) {
+        self.deadline = self.time.map(|t| Instant::now() + t);
+    }
+
+    /// Lifts the deadline until the next [`Budget::start`]; clearing `running` still stops searches.
+    pub fn reset(&mut self) {
+        self.deadline = None;
+    }
+
+    #[must_use]
+    pub fn expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+            || self
+                .running
+                .as_ref()
+                .is_some_and(|r| !r.load(Ordering::Relaxed))
+    }
+}
+
+impl PartialEq for Budget {
+    fn eq(&self, other: &Self) -> bool {
+        let running = match (&self.running, &other.running) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+
+        self.time == other.time && self.deadline == other.deadline && running
+    }
+}
+
+impl Eq for Budget {}
@@ -1,12 +1,11 @@
-use smallvec::SmallVec;
-
 use crate::{
-    input::{Key, Pair},
+    file::pc::{PcTable, PcbParseErr},
+    input::Key,
     pc::{History, Map, generate_all_pc_queues},
     piece::Queue,
     repl::State,
 };
-use std::io::Write;
+use std::{fmt::Display, io::Write};
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Environment<'a> {
@@ -104,65 +103,109 @@ impl<'a> Environment<'a> {
         m
     }
 
-    #[must_use] 
-    pub fn pcs(&self, n: usize, force: bool) -> Map<Queue, History> {
+    /// Loads the PC table for `n` pieces, preferring the `.pcb` form, generating it if neither exists.
+    ///
+    /// A `.pcb` older than the `.pc` next to it, or one that can't be read, is rewritten from the text.
+    ///
+    /// A table generated past `state.budget` is partial and isn't saved.
+    pub fn pcs(&self, n: usize, force: bool) -> Result<Map<Queue, History>, PcsErr> {
         // if it exists as a file, load from file
-        let path = format!("data/{}_{}_{n}.pc", self.state.fingerprint.0, self.flags());
+        let path = format!(
+            "data/{}_{}_{}_{n}.pc",
+            self.state.fingerprint.0,
+            self.flags(),
+            self.state.spin_rule
+        );
+        let bin_path = format!("{path}b");
+
+        if !force {
+            // a broken cache is no worse than none; it's rewritten from the text below
+            if is_fresh(&bin_path, &path)
+                && let Ok(b) = std::fs::read(&bin_path)
+                && let Ok(table) = PcTable::decode(&b)
+            {
+                return Ok(table.into_map());
+            }
 
-        if !force && let Ok(s) = std::fs::read_to_string(&path) {
-            return Self::parse_pcs(&s);
+            if let Ok(s) = std::fs::read_to_string(&path) {
+                let table = PcTable::from_text(&s)?;
+                // only a cache; the text file is still there if this fails
+                let _ = std::fs::write(&bin_path, table.encode());
+                return Ok(table.into_map());
+            }
         }
 
         // otherwise, generate and save to file
-        let mut f = std::fs::File::create(&path).unwrap();
+        let mut out = vec![];
         writeln!(
-            f,
-            "#n={n};kicktable={};total={};{}",
+            out,
+            "#n={n};kicktable={};spin={};total={};{}",
             self.state.fingerprint.0,
+            self.state.spin_rule,
             0,
             self.flags()
-        )
-        .unwrap();
-        generate_all_pc_queues(&mut f, n, self);
+        )?;
+        generate_all_pc_queues::<4>(&mut out, n, self);
+        // checked once, so a table that only ran out of time while being deduped is still saved
+        let complete = !self.state.budget.expired();
 
         // dedup the file
-        let s = std::fs::read_to_string(&path).unwrap();
+        let s = String::from_utf8_lossy(&out);
         let mut lines: Vec<_> = s.lines().collect();
         lines.sort_unstable();
-        lines.dedup_by_key(|x| x.split('=').next().unwrap().trim());
+        lines.dedup_by_key(|x| x.split('=').next().unwrap_or_default().trim());
         let s = lines.join("\n");
 
-        std::fs::write(&path, s).unwrap();
-
-        Self::parse_pcs(&std::fs::read_to_string(&path).unwrap())
+        let table = PcTable::from_text(&s)?;
+        // a table cut short by the budget is only good for this search
+        if complete {
+            std::fs::write(&path, &s)?;
+            std::fs::write(&bin_path, table.encode())?;
+        }
+        Ok(table.into_map())
     }
 
-    #[must_use] 
-    pub fn parse_pcs(s: &str) -> Map<Queue, History> {
-        let mut pcs = Map::new();
+    pub fn parse_pcs(s: &str) -> Result<Map<Queue, History>, PcsErr> {
+        Ok(PcTable::from_text(s)?.into_map())
+    }
+}
 
-        for line in s.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+/// Whether the `.pcb` at `bin` was written no earlier than the `.pc` at `text`, or there's no `.pc`.
+fn is_fresh(bin: &str, text: &str) -> bool {
+    let modified = |path| std::fs::metadata(path).and_then(|m| m.modified());
+    match (modified(bin), modified(text)) {
+        (Ok(b), Ok(t)) => b >= t,
+        (Ok(_), Err(_)) => true,
+        (Err(_), _) => false,
+    }
+}
 
-            let mut parts = line.split('=');
-            let queue = parts.next().unwrap().trim().parse().unwrap();
-            let finesse = parts.next().unwrap().trim();
+#[derive(Debug)]
+pub enum PcsErr {
+    Io(std::io::Error),
+    /// Index of the line that couldn't be parsed.
+    Malformed(usize),
+    Binary(PcbParseErr),
+}
 
-            // convert finesse into Vec<Pair>
-            // Pair is (char, Finesse)
+impl From<PcbParseErr> for PcsErr {
+    fn from(value: PcbParseErr) -> Self {
+        Self::Binary(value)
+    }
+}
 
-            let f = finesse
-                .split_ascii_whitespace()
-                .map(str::parse)
-                .collect::<Result<SmallVec<[Pair; _]>, _>>()
-                .unwrap();
+impl From<std::io::Error> for PcsErr {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
 
-            pcs.insert(queue, History(f));
+impl Display for PcsErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Malformed(idx) => write!(f, "malformed line {}", idx + 1),
+            Self::Binary(e) => write!(f, "{e}"),
         }
-
-        pcs
     }
 }
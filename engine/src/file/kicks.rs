@@ -34,6 +34,16 @@ impl Kicks {
             .iter()
             .find(|x| x.piece == piece.name && x.source == source && x.target == target)
     }
+
+    /// Every kick of one piece, indexed by source and target rotation; the first entry wins, as with [`Kicks::get`].
+    #[must_use]
+    pub fn table(&self, piece: u8) -> [[Option<&Kick>; 4]; 4] {
+        let mut t = [[None; 4]; 4];
+        for k in self.entries.iter().filter(|x| x.piece == piece) {
+            t[k.source as usize][k.target as usize].get_or_insert(k);
+        }
+        t
+    }
 }
 
 impl FromStr for Kicks {
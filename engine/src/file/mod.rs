@@ -2,4 +2,6 @@ pub mod piece;
 pub mod refv;
 pub mod kicks;
 pub mod corners;
+pub mod pc;
+pub mod randomizer;
 
@@ -0,0 +1,247 @@
+use std::fmt::Display;
+
+use smallvec::SmallVec;
+
+use crate::{
+    environment::PcsErr,
+    input::{Finesse, Pair},
+    pc::{History, Map},
+    piece::Queue,
+    spin::Spin,
+};
+
+// format for .pc files, and .pcb files which are their binary form
+//
+// a .pc line is `QUEUE = (P:keys) (P:keys)...`, lines starting with `#` are comments
+//
+// .pcb layout:
+// all integers are little endian
+//
+// magic      b"3RPC"
+// version    u8
+// header     u32 length, then that many bytes of the `#` lines, joined by '\n'
+// flags      u8; bit 0 is set if the text file ended with a newline
+// entries    u32 count, then for each:
+//   queue      u8 length, then the low bytes of the packed queue
+//   history    u8 length, then for each placement:
+//     piece      u8
+//     finesse    u8 length, u8 spin, then the low (length + 1) / 2 bytes of the packed keys
+pub const MAGIC: &[u8; 4] = b"3RPC";
+pub const VERSION: u8 = 1;
+
+/// A PC table in file order, so it can be written back out exactly as it was read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PcTable {
+    pub header: Vec<String>,
+    pub entries: Vec<(Queue, History)>,
+    pub trailing_newline: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PcbParseErr {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidHeader,
+    InvalidFinesse,
+    InvalidSpin(u8),
+}
+
+impl Display for PcbParseErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a .pcb file"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported .pcb version {v}"),
+            Self::Truncated => write!(f, "file ends early"),
+            Self::InvalidHeader => write!(f, "header is not utf-8"),
+            Self::InvalidFinesse => write!(f, "unknown key in finesse"),
+            Self::InvalidSpin(s) => write!(f, "unknown spin {s}"),
+        }
+    }
+}
+
+impl PcTable {
+    pub fn from_text(s: &str) -> Result<Self, PcsErr> {
+        let header = s
+            .lines()
+            .map(str::trim)
+            .filter(|x| x.starts_with('#'))
+            .map(ToString::to_string)
+            .collect();
+
+        let mut entries = vec![];
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split('=');
+            let (Some(queue), Some(finesse)) = (parts.next(), parts.next()) else {
+                return Err(PcsErr::Malformed(idx));
+            };
+            let Ok(queue) = queue.trim().parse();
+
+            // convert finesse into Vec<Pair>
+            // Pair is (char, Finesse)
+
+            let f = finesse
+                .split_ascii_whitespace()
+                .map(str::parse)
+                .collect::<Result<SmallVec<[Pair; _]>, _>>()
+                .map_err(|_| PcsErr::Malformed(idx))?;
+
+            entries.push((queue, History(f)));
+        }
+
+        Ok(Self {
+            header,
+            entries,
+            trailing_newline: s.ends_with('\n'),
+        })
+    }
+
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let mut lines = self.header.clone();
+        for (queue, history) in &self.entries {
+            lines.push(format!(
+                "{} = {}",
+                queue.as_str(),
+                history
+                    .0
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+
+        let mut s = lines.join("\n");
+        if self.trailing_newline {
+            s.push('\n');
+        }
+        s
+    }
+
+    #[must_use]
+    pub fn into_map(self) -> Map<Queue, History> {
+        self.entries.into_iter().collect()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(16 + self.entries.len() * 32);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+
+        let header = self.header.join("\n");
+        out.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        out.extend_from_slice(header.as_bytes());
+        out.push(u8::from(self.trailing_newline));
+
+        out.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (queue, history) in &self.entries {
+            out.push(queue.len() as u8);
+            out.extend(*queue);
+
+            out.push(history.0.len() as u8);
+            for Pair(piece, finesse) in &history.0 {
+                out.push(*piece);
+                out.push(finesse.len);
+                out.push(finesse.spin() as u8);
+                let bytes = finesse.packed().to_le_bytes();
+                out.extend_from_slice(&bytes[..finesse.len.div_ceil(2) as usize]);
+            }
+        }
+
+        out
+    }
+
+    pub fn decode(b: &[u8]) -> Result<Self, PcbParseErr> {
+        let mut r = Reader { b };
+        if r.take(4)? != MAGIC {
+            return Err(PcbParseErr::BadMagic);
+        }
+
+        let version = r.u8()?;
+        if version != VERSION {
+            return Err(PcbParseErr::UnsupportedVersion(version));
+        }
+
+        let header_len = r.u32()? as usize;
+        let header = std::str::from_utf8(r.take(header_len)?)
+            .map_err(|_| PcbParseErr::InvalidHeader)?;
+        let header = if header.is_empty() {
+            vec![]
+        } else {
+            header.split('\n').map(ToString::to_string).collect()
+        };
+        let trailing_newline = r.u8()? & 1 != 0;
+
+        let count = r.u32()? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let len = r.u8()? as usize;
+            let queue: Queue = r.take(len)?.iter().copied().collect();
+
+            let placements = r.u8()? as usize;
+            let mut pairs = SmallVec::with_capacity(placements);
+            for _ in 0..placements {
+                let piece = r.u8()?;
+                let len = r.u8()?;
+                let spin = match r.u8()? {
+                    0 => Spin::None,
+                    1 => Spin::Mini,
+                    2 => Spin::Full,
+                    s => return Err(PcbParseErr::InvalidSpin(s)),
+                };
+
+                let mut bytes = [0; 16];
+                let n = len.div_ceil(2) as usize;
+                if n > bytes.len() {
+                    return Err(PcbParseErr::InvalidFinesse);
+                }
+                bytes[..n].copy_from_slice(r.take(n)?);
+
+                let finesse = Finesse::from_packed(u128::from_le_bytes(bytes), len)
+                    .ok_or(PcbParseErr::InvalidFinesse)?;
+                pairs.push(Pair(piece, finesse.with_spin(spin)));
+            }
+
+            entries.push((queue, History(pairs)));
+        }
+
+        Ok(Self {
+            header,
+            entries,
+            trailing_newline,
+        })
+    }
+}
+
+struct Reader<'a> {
+    b: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], PcbParseErr> {
+        if self.b.len() < n {
+            return Err(PcbParseErr::Truncated);
+        }
+        let (l, r) = self.b.split_at(n);
+        self.b = r;
+        Ok(l)
+    }
+
+    fn u8(&mut self) -> Result<u8, PcbParseErr> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, PcbParseErr> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+}
@@ -1,11 +1,15 @@
 use std::{fmt::Display, str::FromStr};
 
 use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
 
-use crate::common::{
-    color::Color,
-    coordinate::{CoordinateParseErr, Coordinates},
-    rotation::Rotation,
+use crate::{
+    board::Board,
+    common::{
+        color::Color,
+        coordinate::{CoordinateParseErr, Coordinates},
+        rotation::Rotation,
+    },
 };
 
 // format for .piece files
@@ -20,8 +24,18 @@ impl Bag {
         self.shapes.get(&(name, rotation))
     }
 
+    /// Every piece name, once each, in byte order.
     pub fn pieces(&self) -> impl Iterator<Item = u8> {
-        self.shapes.keys().map(|x| x.0)
+        let mut names: Vec<u8> = self.shapes.keys().map(|x| x.0).collect();
+        names.sort_unstable();
+        names.dedup();
+        names.into_iter()
+    }
+
+    /// Number of cells in the piece, going by its north rotation.
+    #[must_use]
+    pub fn minos(&self, name: u8) -> Option<usize> {
+        Some(self.get(name, Rotation::North)?.cells.values.len())
     }
 
     #[must_use]
@@ -68,6 +82,76 @@ pub struct Shape {
     pub color: Color,
     pub rotation: Rotation,
     pub cells: Coordinates<i32>,
+    /// `cells` as board columns, built when the shape is parsed.
+    pub mask: Mask,
+}
+
+/// A shape laid out like [`Board::cols`], so collision is one AND per column instead of a lookup per cell.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Mask {
+    /// Offset of the leftmost column from the piece's center.
+    pub left: i32,
+    /// Offset of the lowest cell from the piece's center.
+    pub bottom: i32,
+    /// Filled cells of each column, with bit 0 at `bottom`.
+    pub cols: SmallVec<[u64; 4]>,
+}
+
+impl Mask {
+    #[must_use]
+    pub fn new(cells: &Coordinates<i32>) -> Self {
+        let (Some(left), Some(bottom)) = (
+            cells.values.iter().map(|c| c.x).min(),
+            cells.values.iter().map(|c| c.y).min(),
+        ) else {
+            return Self::default();
+        };
+
+        let mut cols = SmallVec::new();
+        for c in &cells.values {
+            let x = (c.x - left).unsigned_abs() as usize;
+            if cols.len() <= x {
+                cols.resize(x + 1, 0);
+            }
+            cols[x] |= 1 << (c.y - bottom);
+        }
+
+        Self { left, bottom, cols }
+    }
+
+    /// Where the lowest, leftmost corner of the mask lands with the center at `(x, y)`; `None` if off the board.
+    fn origin(&self, x: usize, y: usize) -> Option<(usize, u32)> {
+        let x = x.checked_add_signed(self.left as isize)?;
+        let y = y.checked_add_signed(self.bottom as isize)?;
+        Some((x, u32::try_from(y).ok()?))
+    }
+
+    /// Whether the shape centered at `(x, y)` is inside the walls and above the floor without overlapping anything.
+    #[inline]
+    #[must_use]
+    pub fn fits<const W: usize>(&self, board: &Board<W>, x: usize, y: usize) -> bool {
+        let Some((x, y)) = self.origin(x, y) else {
+            return false;
+        };
+        let Some(cols) = board.cols.get(x..x + self.cols.len()) else {
+            return false;
+        };
+
+        cols.iter()
+            .zip(&self.cols)
+            .all(|(&c, &m)| c.checked_shr(y).unwrap_or(0) & m == 0)
+    }
+
+    /// Fills the shape's cells in, centered at `(x, y)`; cells off the board are dropped.
+    pub fn lock<const W: usize>(&self, board: &mut Board<W>, x: usize, y: usize) {
+        let Some((x, y)) = self.origin(x, y) else {
+            return;
+        };
+
+        for (c, &m) in board.cols.iter_mut().skip(x).zip(&self.cols) {
+            *c |= m.checked_shl(y).unwrap_or(0);
+        }
+    }
 }
 
 impl Display for Shape {
@@ -109,6 +193,7 @@ impl FromStr for Shape {
                 name,
                 color,
                 rotation,
+                mask: Mask::new(&cells),
                 cells,
             })
         } else {
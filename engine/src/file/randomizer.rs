@@ -0,0 +1,333 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    file::piece::Bag,
+    randomizer::{Draw, Randomizer, Rng},
+};
+
+// format for .rand files, one `key=value` per line; `#` starts a comment
+//
+// pieces=ZLOSIJT     pieces to deal, from the .piece file; every piece in it if left out
+// copies=1           copies of each piece per bag; 0 deals every piece at random instead
+// extras=3,2,1,1,0   extra pieces added to each bag in turn; the last count repeats
+// extras_from=pool   `random`: any piece, `pool`: from a shuffled set of the pieces, refilled when short
+// history=4          how many of the latest pieces count as repeats
+// rerolls=4          how many times a repeat is drawn again
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Custom {
+    /// Order matters: bags are shuffled starting from it.
+    pub pieces: Vec<u8>,
+    pub copies: u8,
+    pub extras: Vec<u8>,
+    pub extras_from: ExtraSource,
+    pub history: usize,
+    pub rerolls: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum ExtraSource {
+    #[default]
+    Random,
+    Pool,
+}
+
+impl Default for Custom {
+    fn default() -> Self {
+        Self {
+            pieces: vec![],
+            copies: 1,
+            extras: vec![],
+            extras_from: ExtraSource::Random,
+            history: 0,
+            rerolls: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CustomParseErr {
+    Malformed(usize),
+    UnknownKey(String),
+    InvalidValue(&'static str, String),
+    /// A piece the loaded `.piece` file doesn't define.
+    UnknownPiece(u8),
+    NoPieces,
+    NotFound(String),
+}
+
+impl Display for CustomParseErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(idx) => write!(f, "malformed line {}", idx + 1),
+            Self::UnknownKey(k) => write!(f, "`{k}` is not a key"),
+            Self::InvalidValue(k, v) => write!(f, "`{v}` is not a valid {k}"),
+            Self::UnknownPiece(p) => write!(f, "`{}` is not a piece", *p as char),
+            Self::NoPieces => write!(f, "no pieces to deal"),
+            Self::NotFound(name) => write!(f, "`{name}` is not a randomizer"),
+        }
+    }
+}
+
+impl Display for ExtraSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Random => "random",
+                Self::Pool => "pool",
+            }
+        )
+    }
+}
+
+impl FromStr for ExtraSource {
+    type Err = CustomParseErr;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Self::Random),
+            "pool" => Ok(Self::Pool),
+            c => Err(CustomParseErr::InvalidValue("extras_from", c.to_string())),
+        }
+    }
+}
+
+impl Display for Custom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "pieces={}", String::from_utf8_lossy(&self.pieces))?;
+        writeln!(f, "copies={}", self.copies)?;
+        writeln!(
+            f,
+            "extras={}",
+            self.extras
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        )?;
+        writeln!(f, "extras_from={}", self.extras_from)?;
+        writeln!(f, "history={}", self.history)?;
+        write!(f, "rerolls={}", self.rerolls)
+    }
+}
+
+impl FromStr for Custom {
+    type Err = CustomParseErr;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn value<T: FromStr>(key: &'static str, v: &str) -> Result<T, CustomParseErr> {
+            v.parse()
+                .map_err(|_| CustomParseErr::InvalidValue(key, v.to_string()))
+        }
+
+        let mut c = Self::default();
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some((k, v)) = line.split_once('=') else {
+                return Err(CustomParseErr::Malformed(idx));
+            };
+
+            let v = v.trim();
+            match k.trim() {
+                "pieces" => c.pieces = v.bytes().collect(),
+                "copies" => c.copies = value("copies", v)?,
+                "extras" => {
+                    c.extras = v
+                        .split(',')
+                        .filter(|x| !x.trim().is_empty())
+                        .map(|x| value("extras", x.trim()))
+                        .collect::<Result<_, _>>()?;
+                }
+                "extras_from" => c.extras_from = v.parse()?,
+                "history" => c.history = value("history", v)?,
+                "rerolls" => c.rerolls = value("rerolls", v)?,
+                k => return Err(CustomParseErr::UnknownKey(k.to_string())),
+            }
+        }
+
+        Ok(c)
+    }
+}
+
+impl Custom {
+    /// Checks the pieces against `bag`, taking all of its pieces if none were listed.
+    pub fn with_bag(mut self, bag: &Bag) -> Result<Self, CustomParseErr> {
+        if self.pieces.is_empty() {
+            self.pieces = bag.pieces().collect();
+        }
+
+        if let Some(&p) = self.pieces.iter().find(|&&p| !bag.pieces().any(|x| x == p)) {
+            return Err(CustomParseErr::UnknownPiece(p));
+        }
+
+        if self.pieces.is_empty() {
+            return Err(CustomParseErr::NoPieces);
+        }
+
+        // a pool only ever holds one of each piece
+        if self.extras_from == ExtraSource::Pool
+            && let Some(&e) = self
+                .extras
+                .iter()
+                .find(|&&e| usize::from(e) > self.pieces.len())
+        {
+            return Err(CustomParseErr::InvalidValue("extras", e.to_string()));
+        }
+
+        Ok(self)
+    }
+
+    fn extras_for(&self, bagid: usize) -> usize {
+        self.extras
+            .get(bagid)
+            .or(self.extras.last())
+            .copied()
+            .map(usize::from)
+            .unwrap_or_default()
+    }
+
+    /// The next bag, unshuffled.
+    fn fill(&self, rng: &mut Rng) -> Vec<u8> {
+        let mut bag: Vec<u8> = (0..self.copies).flat_map(|_| self.pieces.clone()).collect();
+        let count = self.extras_for(rng.bagid);
+        rng.bagid += 1;
+
+        match self.extras_from {
+            ExtraSource::Random => {
+                for _ in 0..count {
+                    bag.push(*rng.pick(&self.pieces));
+                }
+            }
+            ExtraSource::Pool => {
+                if rng.extrabag.len() < count {
+                    let mut pool = self.pieces.clone();
+                    rng.shuffle_array(&mut pool);
+                    rng.extrabag = pool;
+                }
+                bag.extend(rng.extrabag.drain(..count));
+            }
+        }
+
+        bag
+    }
+}
+
+impl Randomizer for Custom {
+    fn pieces(&self) -> &[u8] {
+        &self.pieces
+    }
+
+    fn deal(&self, rng: &mut Rng) -> Vec<u8> {
+        // without rerolls the bag can be shuffled in one go, the way TETR.IO does it
+        if self.copies > 0 && self.rerolls == 0 {
+            let mut bag = self.fill(rng);
+            rng.shuffle_array(&mut bag);
+            return bag;
+        }
+
+        if self.copies > 0 && rng.bag.is_empty() {
+            rng.bag = self.fill(rng);
+        }
+
+        let source = if self.copies > 0 {
+            rng.bag.clone()
+        } else {
+            self.pieces.clone()
+        };
+
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let roll = |rng: &mut Rng| {
+            ((rng.next_float() * source.len() as f64).floor() as usize).min(source.len() - 1)
+        };
+
+        let mut idx = roll(rng);
+        for _ in 0..self.rerolls {
+            if !rng.history.contains(&source[idx]) {
+                break;
+            }
+            idx = roll(rng);
+        }
+
+        let piece = source[idx];
+        if self.copies > 0 {
+            rng.bag.remove(idx);
+        }
+
+        rng.history.push_front(piece);
+        rng.history.truncate(self.history);
+        vec![piece]
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn next(&self, state: &Draw) -> Vec<(usize, f64, Draw)> {
+        let n = self.pieces.len();
+        let states = if self.copies == 0 {
+            // every piece is equally likely, and nothing but the history changes
+            vec![(
+                Draw {
+                    bag: smallvec::smallvec![1; n],
+                    ..state.clone()
+                },
+                1.0,
+            )]
+        } else if state.is_empty() {
+            let count = self.extras_for(state.bagid);
+            let refilled = vec![(state.refill(n, self.copies), 1.0)];
+            match self.extras_from {
+                ExtraSource::Random => Draw::random_extras(refilled, count),
+                ExtraSource::Pool => Draw::pooled_extras(refilled, count),
+            }
+        } else {
+            vec![(state.clone(), 1.0)]
+        };
+
+        let mut out = vec![];
+        for (s, p) in states {
+            let total: u8 = s.bag.iter().sum();
+            let repeat = |i: usize| s.history.contains(&i);
+            // chance that a single draw is a repeat
+            let q = (0..n)
+                .filter(|&i| repeat(i))
+                .map(|i| f64::from(s.bag[i]) / f64::from(total))
+                .sum::<f64>();
+
+            for (i, w, bag) in Draw::take(&s.bag) {
+                // a repeat only sticks once the rerolls run out; anything else can come from any attempt
+                let w = if repeat(i) {
+                    w * q.powi(self.rerolls as i32)
+                } else {
+                    w * (0..=self.rerolls as i32).map(|k| q.powi(k)).sum::<f64>()
+                };
+
+                let bag = if self.copies == 0 {
+                    state.bag.clone()
+                } else {
+                    bag
+                };
+                let next = Draw { bag, ..s.clone() }.remember(i, self.history);
+                out.push((i, p * w, next));
+            }
+        }
+
+        out
+    }
+
+    fn period(&self) -> usize {
+        if self.copies == 0 {
+            return self.history + 1;
+        }
+
+        let size = self.pieces.len() * usize::from(self.copies);
+        let bags: usize = (0..self.extras.len().max(1))
+            .map(|b| size + self.extras_for(b))
+            .sum();
+        bags + self.history
+    }
+}
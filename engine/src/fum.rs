@@ -1,35 +1,43 @@
 use fumen::{CellColor, Fumen, Page};
 use itertools::Itertools;
 
-use crate::ren::PathItem;
+use crate::{board::Board, environment::Environment, pc::History};
 
-pub fn tofumen(t: Vec<PathItem>) -> Fumen {
+/// One page per placement in `history`, replayed from `board`, each showing the board after it locks.
+///
+/// Stops at the first placement that doesn't fit.
+#[must_use]
+pub fn tofumen<const W: usize>(mut board: Board<W>, history: &History, env: &Environment) -> Fumen {
     Fumen {
         guideline: true,
-        pages: t
-            .into_iter()
-            .map(|p| Page {
-                comment: Some(format!("{} {}", p.1, p.2.short())),
-                garbage_row: [CellColor::Empty; 10],
-                lock: false,
-                piece: None,
-                mirror: false,
-                rise: false,
-                field: (0..23)
-                    .map(|y| {
-                        (0..10)
-                            .map(|x| {
-                                if p.0.get(x, y) {
-                                    CellColor::Grey
-                                } else {
-                                    CellColor::Empty
-                                }
-                            })
-                            .collect_array::<10>()
-                            .unwrap()
-                    })
-                    .collect_array::<23>()
-                    .unwrap(),
+        pages: history
+            .0
+            .iter()
+            .map_while(|&pair| {
+                board = pair.locate(board, env)?.board;
+                Some(Page {
+                    comment: Some(pair.to_string()),
+                    garbage_row: [CellColor::Empty; 10],
+                    lock: false,
+                    piece: None,
+                    mirror: false,
+                    rise: false,
+                    field: (0..23)
+                        .map(|y| {
+                            (0..10)
+                                .map(|x| {
+                                    if board.get(x, y) {
+                                        CellColor::Grey
+                                    } else {
+                                        CellColor::Empty
+                                    }
+                                })
+                                .collect_array::<10>()
+                                .unwrap()
+                        })
+                        .collect_array::<23>()
+                        .unwrap(),
+                })
             })
             .collect(),
     }
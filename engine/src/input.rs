@@ -7,36 +7,62 @@ use crate::{
     board::Board,
     common::{coordinate::Coordinate, rotation::Rotation},
     environment::Environment,
+    file::{kicks::Kick, piece::Mask},
     piece::Piece,
+    spin::Spin,
 };
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct Input<'a> {
-    pub board: Board,
+pub struct Input<'a, const W: usize = 4> {
+    pub board: Board<W>,
     pub piece: Piece,
     pub environment: &'a Environment<'a>,
 
-    last_successful_action: Option<Key>,
+    pub(crate) last_successful_action: Option<Key>,
+    /// Offset of the kick test used by the last successful rotation.
+    pub(crate) last_kick: Option<Coordinate<i32>>,
+    /// The piece's shape in each rotation, looked up once so moves don't go through the `.piece` file.
+    masks: [Option<&'a Mask>; 4],
+    /// Same for the `.kick` file.
+    kicks: [[Option<&'a Kick>; 4]; 4],
 }
 
-impl<'a> Input<'a> {
+impl<'a, const W: usize> Input<'a, W> {
+    /// Column pieces spawn in; 1 on a 4-wide board (@usMath says its 1), 4 on a 10-wide one.
+    pub const SPAWN_X: usize = W.saturating_sub(1) / 2;
+
     #[must_use]
-    pub fn new(board: Board, p: u8, environment: &'a Environment) -> Self {
+    pub fn new(board: Board<W>, p: u8, environment: &'a Environment) -> Self {
         let piece = Piece {
             name: p,
             rotation: Rotation::North,
-            // @usMath says its 1
-            location: Coordinate::new(1, board.height() + 4),
+            location: Coordinate::new(Self::SPAWN_X, board.height() + 4),
         };
 
+        let bag = &environment.state.bag;
+        let masks = [
+            Rotation::North,
+            Rotation::East,
+            Rotation::South,
+            Rotation::West,
+        ]
+        .map(|r| bag.get(p, r).map(|s| &s.mask));
+
         Self {
             board,
             piece,
             environment,
             last_successful_action: None,
+            last_kick: None,
+            masks,
+            kicks: environment.state.kicks.table(p),
         }
     }
 
+    fn mask(&self) -> Option<&'a Mask> {
+        self.masks[self.piece.rotation as usize]
+    }
+
     #[must_use]
     pub fn fingerprint(&self) -> Piece {
         self.piece
@@ -44,8 +70,8 @@ impl<'a> Input<'a> {
 
     #[must_use]
     pub fn is_valid(&self) -> bool {
-        let mut c = self.piece.cells(self.environment);
-        c.all(|x| matches!(x, Some(v) if v.x < self.board.width() && !self.board.get(v.x,v.y)))
+        let Coordinate { x, y } = self.piece.location;
+        self.mask().is_some_and(|m| m.fits(&self.board, x, y))
     }
 
     pub fn move_left(&mut self) {
@@ -158,11 +184,7 @@ impl<'a> Input<'a> {
     pub fn rotate_cw(&mut self) {
         let ir = self.piece.rotation;
         let fr = self.piece.rotation.rotate_cw();
-        let k = self
-            .environment
-            .state
-            .kicks
-            .get(&self.piece, ir, fr)
+        let k = self.kicks[ir as usize][fr as usize]
             .expect("piece should have this interaction defined in the .kick file");
         let ipos = self.piece.location;
 
@@ -184,6 +206,7 @@ impl<'a> Input<'a> {
                 }
 
                 self.last_successful_action = Some(Key::RotateCW);
+                self.last_kick = Some(test);
                 return;
             }
         }
@@ -193,11 +216,7 @@ impl<'a> Input<'a> {
     pub fn rotate_ccw(&mut self) {
         let ir = self.piece.rotation;
         let fr = self.piece.rotation.rotate_ccw();
-        let k = self
-            .environment
-            .state
-            .kicks
-            .get(&self.piece, ir, fr)
+        let k = self.kicks[ir as usize][fr as usize]
             .expect("piece should have this interaction defined in the .kick file");
         let ipos = self.piece.location;
 
@@ -219,6 +238,7 @@ impl<'a> Input<'a> {
                 }
 
                 self.last_successful_action = Some(Key::RotateCCW);
+                self.last_kick = Some(test);
                 return;
             }
         }
@@ -228,11 +248,7 @@ impl<'a> Input<'a> {
     pub fn rotate_180(&mut self) {
         let ir = self.piece.rotation;
         let fr = self.piece.rotation.rotate_180();
-        let k = self
-            .environment
-            .state
-            .kicks
-            .get(&self.piece, ir, fr)
+        let k = self.kicks[ir as usize][fr as usize]
             .expect("piece should have this interaction defined in the .kick file");
         let ipos = self.piece.location;
 
@@ -254,6 +270,7 @@ impl<'a> Input<'a> {
                 }
 
                 self.last_successful_action = Some(Key::Rotate180);
+                self.last_kick = Some(test);
                 return;
             }
         }
@@ -276,58 +293,28 @@ impl<'a> Input<'a> {
     }
 
     #[must_use]
-    pub fn place(mut self, hd: bool) -> Board {
+    pub fn place(mut self, hd: bool) -> Placement<W> {
         if hd {
             self.sonic_drop();
         }
 
-        for c in self.piece.cells(self.environment).flatten() {
-            self.board.set(c.x, c.y, true);
+        let spin = self.spin();
+
+        if let Some(m) = self.mask() {
+            m.lock(&mut self.board, self.piece.location.x, self.piece.location.y);
         }
 
+        let cleared = self.board.full_rows();
         self.board.skim();
 
-        self.board
-    }
-
-    #[must_use]
-    pub fn is_spin(&self) -> bool {
-        // dbg!(self.last_successful_action);
-        match self.last_successful_action {
-            Some(Key::RotateCW | Key::RotateCCW | Key::Rotate180) => {}
-            _ => return false,
-        }
-
-        let Some(corner_set) = self
-            .environment
-            .state
-            .corners
-            .get(self.piece.name, self.piece.rotation)
-        else {
-            return false;
-        };
-
-        let mut filled = 0;
-        #[allow(
-            clippy::cast_possible_wrap,
-            clippy::cast_possible_truncation,
-            clippy::cast_sign_loss
-        )]
-        for corner in &corner_set.corners.values {
-            let x = self.piece.location.x as i32 + corner.x;
-            let y = self.piece.location.y as i32 + corner.y;
-
-            if x < 0
-                || y < 0
-                || x >= self.board.width() as i32
-                || y >= self.board.height() as i32
-                || self.board.get(x as usize, y as usize)
-            {
-                filled += 1;
-            }
+        Placement {
+            board: self.board,
+            piece: self.piece,
+            lines: cleared.count_ones(),
+            cleared,
+            spin,
+            pc: self.board.is_empty(),
         }
-
-        filled >= 3
     }
 
     pub fn apply(&mut self, f: Finesse) {
@@ -339,6 +326,20 @@ impl<'a> Input<'a> {
     }
 }
 
+/// The outcome of locking a piece.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Placement<const W: usize = 4> {
+    /// Board after line clears.
+    pub board: Board<W>,
+    /// Where the piece locked.
+    pub piece: Piece,
+    pub lines: u32,
+    /// Mask of the rows that were cleared, relative to the board before the clear.
+    pub cleared: u64,
+    pub spin: Spin,
+    pub pc: bool,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Key {
     MoveLeft,
@@ -395,14 +396,15 @@ impl FromStr for Key {
         match s.to_ascii_lowercase().as_str() {
             "dl" => Ok(Self::DasLeft),
             "dr" => Ok(Self::DasRight),
-            "l" => Ok(Self::MoveLeft),
-            "r" => Ok(Self::MoveRight),
-            "f" => Ok(Self::Rotate180),
-            "ccw" => Ok(Self::RotateCCW),
-            "cw" => Ok(Self::RotateCW),
+            "l" | "moveleft" => Ok(Self::MoveLeft),
+            "r" | "moveright" => Ok(Self::MoveRight),
+            "f" | "rotate180" => Ok(Self::Rotate180),
+            "ccw" | "rotateccw" => Ok(Self::RotateCCW),
+            "cw" | "rotatecw" => Ok(Self::RotateCW),
             "fd" => Ok(Self::SoftDrop),
-            "sd" => Ok(Self::SonicDrop),
-            "h" => Ok(Self::Hold),
+            // the long names are what the repl prints, where drops are sonic
+            "sd" | "softdrop" => Ok(Self::SonicDrop),
+            "h" | "hold" => Ok(Self::Hold),
             c => Err(format!("unknown variant {c}")),
         }
     }
@@ -412,7 +414,7 @@ impl FromStr for Key {
 pub struct Finesse {
     packed: u128,
     pub len: u8,
-    spin: bool,
+    spin: Spin,
 }
 
 impl Extend<Key> for Finesse {
@@ -451,12 +453,15 @@ impl Iterator for FinesseIterator {
 }
 
 impl Finesse {
+    /// Most keys that fit in the packed representation.
+    pub const MAX_LEN: u8 = 32;
+
     #[must_use]
     pub fn new() -> Self {
         Self {
             packed: 0,
             len: 0,
-            spin: false,
+            spin: Spin::None,
         }
     }
 
@@ -479,7 +484,7 @@ impl Finesse {
             }
         }
 
-        i
+        i.with_spin(self.spin)
     }
 
     pub fn push(&mut self, key: Key) {
@@ -499,11 +504,40 @@ impl Finesse {
     }
 
     #[must_use]
-    pub fn with_spin(mut self, s: bool) -> Self {
+    pub fn with_spin(mut self, s: Spin) -> Self {
         self.spin = s;
         self
     }
 
+    #[must_use]
+    pub fn spin(&self) -> Spin {
+        self.spin
+    }
+
+    /// The keys, 4 bits each with the first key in the lowest bits.
+    #[must_use]
+    pub fn packed(&self) -> u128 {
+        self.packed
+    }
+
+    /// Inverse of [`Finesse::packed`]; `None` if any of the first `len` keys is invalid or anything follows them.
+    #[must_use]
+    pub fn from_packed(packed: u128, len: u8) -> Option<Self> {
+        if len > Self::MAX_LEN || (len < Self::MAX_LEN && packed >> (u32::from(len) * 4) != 0) {
+            return None;
+        }
+
+        if (0..len).any(|i| (packed >> (i * 4)) & 0x0F > Key::Hold as u128) {
+            return None;
+        }
+
+        Some(Self {
+            packed,
+            len,
+            spin: Spin::None,
+        })
+    }
+
     #[must_use]
     pub fn short(self) -> String {
         let mut v = vec![];
@@ -535,7 +569,7 @@ impl Debug for Finesse {
         f.debug_tuple("Finesse")
             .field(&self.packed)
             .field(&self.len)
-            .field(&i32::from(self.spin))
+            .field(&self.spin)
             .finish()
     }
 }
@@ -568,17 +602,46 @@ impl FromStr for Finesse {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Pair(pub u8, pub Finesse);
 
+impl Pair {
+    /// Replays this placement on `board` to find where it locks.
+    #[must_use]
+    pub fn locate<const W: usize>(self, board: Board<W>, env: &Environment) -> Option<Placement<W>> {
+        let mut i = Input::new(board, self.0, env);
+        i.apply(self.1);
+        i.sonic_drop();
+        if !i.is_valid() {
+            return None;
+        }
+        Some(i.place(false))
+    }
+}
+
 impl Display for Pair {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}:{})", self.0 as char, self.1.short())
+        write!(f, "({}:{})", self.0 as char, self.1.short())?;
+        if self.1.spin.is_spin() {
+            write!(f, "[{}]", self.1.spin)?;
+        }
+
+        Ok(())
     }
 }
 
-// this is of format `(X:cw,ccw,sd,...)`
+// this is of format `(X:cw,ccw,sd,...)`, followed by `[spin]` or `[mini]` for spins
 impl FromStr for Pair {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.trim();
+        let mut s = s.trim();
+        let mut spin = Spin::None;
+        if let Some(rest) = s.strip_suffix(']')
+            && let Some(i) = rest.rfind('[')
+        {
+            spin = rest[i + 1..]
+                .parse()
+                .map_err(|_| format!("unknown spin {}", &rest[i + 1..]))?;
+            s = &rest[..i];
+        }
+
         if !s.starts_with('(') || !s.ends_with(')') {
             return Err("finesse must start with '(' and end with ')'".into());
         }
@@ -595,6 +658,6 @@ impl FromStr for Pair {
         let finesse_str = parts.next().ok_or_else(|| "missing finesse".to_string())?;
         let finesse: Finesse = finesse_str.parse()?;
 
-        Ok(Self(piece, finesse))
+        Ok(Self(piece, finesse.with_spin(spin)))
     }
 }
@@ -9,13 +9,18 @@
 #![deny(unused_qualifications)]
 
 pub mod board;
+pub mod budget;
 pub mod common;
 pub mod environment;
 pub mod file;
+pub mod fum;
 pub mod input;
 pub mod pc;
 pub mod piece;
 pub mod randomizer;
 pub mod ren;
 pub mod repl;
+pub mod sim;
+pub mod spin;
+pub mod tbp;
 
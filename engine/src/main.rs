@@ -1,12 +1,30 @@
 use std::{collections::HashMap, env, io};
 
-use engine::{file::{corners::Corners, kicks::Kicks, piece::Bag}, repl::{Repl, State}};
+use engine::{budget::Budget, file::{corners::Corners, kicks::Kicks, pc::PcTable, piece::Bag}, randomizer::{AnyRandomizer, Rng}, repl::{Repl, State}, spin::SpinRule, tbp::Tbp};
 fn main() {
-    let mut args = env::args();
+    let mut args = env::args().peekable();
     args.next();
+    if args.next_if(|x| x == "--convert").is_some() {
+        let (i, o) = (args.next().unwrap(), args.next().unwrap());
+        convert(&i, &o);
+        return;
+    }
+
+    if args.next_if(|x| x == "--seed").is_some() {
+        let bag: Bag = std::fs::read_to_string("data/tetromino.piece").unwrap().parse().unwrap();
+        let randomizer = AnyRandomizer::load(&args.next().unwrap(), &bag).unwrap();
+        let observed = args.next().unwrap();
+        let limit = args.next().map_or(16, |x| x.parse().unwrap());
+        seed(randomizer, &observed, limit);
+        return;
+    }
+
+    let tbp = args.next_if(|x| x == "--tbp").is_some();
     let kn = args.next().unwrap();
     let bn = args.next().unwrap_or("tetromino".to_string());
     let cn = args.next().unwrap_or("handheld".to_string());
+    let spin_rule: SpinRule = args.next().map_or(SpinRule::Handheld, |x| x.parse().unwrap());
+    let randomizer = args.next();
     let k_file = std::fs::read_to_string(format!("data/{kn}.kick")).unwrap();
     let b_file = std::fs::read_to_string(format!("data/{bn}.piece")).unwrap();
     let c_file = std::fs::read_to_string(format!("data/{cn}.corners")).unwrap();
@@ -14,16 +32,49 @@ fn main() {
     let kicks: Kicks = k_file.parse().unwrap();
     let bag: Bag = b_file.parse().unwrap();
     let corners: Corners = c_file.parse().unwrap();
+    let randomizer = randomizer.map_or_else(AnyRandomizer::default, |x| AnyRandomizer::load(&x, &bag).unwrap());
 
     let s = State {
         kicks,
         bag,
         corners,
+        spin_rule,
+        randomizer,
         fingerprint: (kn,bn,cn),
         pcs: HashMap::new(),
+        session: None,
+        budget: Budget::default(),
     };
 
+    if tbp {
+        Tbp::new(io::stdin(), io::stdout(), s).run();
+        return;
+    }
+
     let repl = Repl::new(io::stdin(), io::stdout(), s);
     let handle = repl.spawn();
     handle.handle.join().unwrap();
 }
+
+/// Converts between `.pc` and `.pcb`, going by the extension of `i`.
+fn convert(i: &str, o: &str) {
+    if i.ends_with(".pcb") {
+        let table = PcTable::decode(&std::fs::read(i).unwrap()).unwrap();
+        std::fs::write(o, table.to_text()).unwrap();
+    } else {
+        let table = PcTable::from_text(&std::fs::read_to_string(i).unwrap()).unwrap();
+        std::fs::write(o, table.encode()).unwrap();
+    }
+}
+
+/// Prints every seed that deals `observed`, followed by the pieces it deals after that.
+fn seed(randomizer: AnyRandomizer, observed: &str, limit: usize) {
+    for seed in Rng::recover(observed.as_bytes(), &randomizer, 1..=Rng::MAX_SEED, limit) {
+        let next: String = Rng::pieces(seed, randomizer.clone())
+            .skip(observed.len())
+            .take(14)
+            .map(char::from)
+            .collect();
+        println!("{seed} {next}");
+    }
+}
@@ -1,12 +1,21 @@
 use std::{
-    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     hash::Hash,
     io::Write,
+    sync::{Arc, RwLock},
 };
 
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rustc_hash::{FxHashMap, FxHashSet};
 use smallvec::{SmallVec, smallvec};
 
-use crate::{board::Board, environment::Environment, input::Pair, piece::Queue};
+use crate::{
+    board::Board,
+    environment::Environment,
+    input::{Finesse, Pair, Placement},
+    piece::Queue,
+    randomizer::Randomizer,
+};
 
 #[derive(Clone, Eq, Debug)]
 pub struct History(pub SmallVec<[Pair; 8]>);
@@ -68,71 +77,119 @@ pub type Map<K, V> = BTreeMap<K, V>;
 /// I = I ()
 /// JJ = J () J (f r)
 /// TTSZ = [longer finesse...]
-pub fn generate_all_pc_queues(buf: &mut impl Write, n: usize, env: &Environment) {
-    // forwards_saved_transitions = {}  # (board_hash, piece) -> next_board_list
-    let mut forwards_saved_transitions = HashMap::new();
-    // forwards_queue = deque()
-    let mut forwards_queue: VecDeque<(Board, History)> = VecDeque::new();
-
-    // forwards_reachable_states = defaultdict(set)  # board_hash -> queue_set
-
-    let mut visited = HashSet::new();
-    let mut vq = HashSet::new();
-
-    forwards_queue.push_back((Board::empty(), History(smallvec![])));
-
-    // let mut i: usize = 0;
-    // let mut max = 7usize.pow(n as u32);
-
-    while let Some(current) = forwards_queue.pop_front() {
-        let key = (current.0, current.1.queue_str());
-        if visited.contains(&key) {
-            continue;
+///
+/// The board width is taken from `W`; `Environment::pcs` uses the default 4-wide board.
+///
+/// Each depth of the search is expanded in parallel a chunk at a time, then merged in queue order so the
+/// output is the same as a single-threaded breadth-first search.
+///
+/// When several histories make the same queue, the first one found is kept: pieces are tried in byte order
+/// and placements in [`Board::get_next_boards`] order, so a table comes out the same on every run.
+///
+/// Once `env.state.budget` runs out, only the queues already written are kept; shorter ones come first.
+pub fn generate_all_pc_queues<const W: usize>(buf: &mut impl Write, n: usize, env: &Environment) {
+    // states expanded at once; bounds how many children are held before they're deduped
+    const CHUNK: usize = 1 << 12;
+
+    let pieces: Vec<u8> = env.state.bag.pieces().collect();
+
+    // (board, piece) -> next boards
+    let transitions: RwLock<FxHashMap<(Board<W>, u8), Arc<[(Placement<W>, Finesse)]>>> =
+        RwLock::new(FxHashMap::default());
+    // the same, but only the placements that pc, for the last piece of a queue
+    let finals: RwLock<FxHashMap<(Board<W>, u8), Arc<[(Placement<W>, Finesse)]>>> =
+        RwLock::new(FxHashMap::default());
+    // boards reached by each queue so far
+    let mut visited = FxHashSet::default();
+    // queues already known to PC
+    let mut vq = FxHashSet::default();
+
+    let mut frontier: Vec<(Board<W>, History)> = vec![(Board::empty(), History(smallvec![]))];
+
+    let expand = |board: &Board<W>, history: &History| {
+        let mut out = vec![];
+        if env.state.budget.expired() {
+            return out;
         }
 
-        visited.insert(key.clone());
-        if vq.contains(&current.1.queue()) {
-            continue;
-        }
+        // the last piece of a queue only counts if it clears the board
+        let last = history.0.len() + 1 >= n;
+        let cache = if last { &finals } else { &transitions };
 
-        // i += 1;
-        let (board, history) = current;
-        
         // check each possible next piece
-        for piece in env.state.bag.pieces() {
-            forwards_saved_transitions.entry((board, piece)).or_insert_with(|| board.get_next_boards(piece, env));
-
-            for &(next_board, f) in forwards_saved_transitions.get(&(board, piece)).unwrap() {
+        for &piece in &pieces {
+            let cached = cache.read().unwrap().get(&(*board, piece)).cloned();
+            let next = cached.unwrap_or_else(|| {
+                let mut next = board.get_next_boards(piece, env);
+                if last {
+                    next.retain(|(placement, _)| placement.pc);
+                }
+                cache
+                    .write()
+                    .unwrap()
+                    .entry((*board, piece))
+                    .or_insert(next.into())
+                    .clone()
+            });
+
+            for &(placement, f) in next.iter() {
                 // track reachable board states
-                if next_board.height() <= n {
-                    let new_history: History = {
-                        let mut n = history.0.clone();
-                        n.push(Pair(piece, f));
-                        History(n)
-                    };
-                    if new_history.0.len() < n && !next_board.is_empty() {
-                        forwards_queue.push_back((next_board, new_history.clone()));
-                    } else if next_board.is_empty() {
-                        if !vq.insert(new_history.queue()) {
-                            continue;
-                        }
+                if placement.board.height() <= n && (placement.pc || !last) {
+                    let mut h = history.0.clone();
+                    h.push(Pair(piece, f));
+                    out.push((placement.board, History(h), placement.pc));
+                }
+            }
+        }
 
-                        writeln!(
-                            buf,
-                            "{} = {}",
-                            new_history.queue_str(),
-                            new_history
-                                .0
-                                .iter()
-                                .map(ToString::to_string)
-                                .collect::<Vec<_>>()
-                                .join(" ")
-                        )
-                        .unwrap();
+        out
+    };
+
+    // what's been written so far is kept if the budget runs out
+    while !frontier.is_empty() && !env.state.budget.expired() {
+        // queues that pc'd since their state was reached don't need to go any further
+        let level: Vec<_> = frontier
+            .into_iter()
+            .filter(|(_, history)| !vq.contains(&history.queue()))
+            .collect();
+
+        let mut next_frontier = vec![];
+        for chunk in level.chunks(CHUNK) {
+            let children: Vec<Vec<(Board<W>, History, bool)>> = chunk
+                .par_iter()
+                .map(|(board, history)| expand(board, history))
+                .collect();
+
+            for (next_board, new_history, pc) in children.into_iter().flatten() {
+                if new_history.0.len() < n && !pc {
+                    // keep the first occurrence of every state, as a FIFO queue would; this has to
+                    // stay sequential for the output to be deterministic, and happens here so
+                    // duplicates never pile up in the frontier
+                    if visited.insert((next_board, new_history.queue())) {
+                        next_frontier.push((next_board, new_history));
+                    }
+                } else if pc {
+                    if !vq.insert(new_history.queue()) {
+                        continue;
                     }
+
+                    writeln!(
+                        buf,
+                        "{} = {}",
+                        new_history.queue_str(),
+                        new_history
+                            .0
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    )
+                    .unwrap();
                 }
             }
         }
+
+        frontier = next_frontier;
     }
 }
 
@@ -185,40 +242,100 @@ pub fn get_queue_orders(queue: Queue) -> Vec<Queue> {
     results
 }
 
-#[must_use] 
+/// Every pc [`max_pcs_in_queue`] settled on, in the order they're made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub pcs: Vec<PlannedPc>,
+    /// Held piece after the last pc, or the starting one if there are none.
+    pub hold: Option<u8>,
+    /// Pieces after the last pc, not counting hold.
+    pub rest: Queue,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlannedPc {
+    pub history: History,
+    /// Held piece once this pc is done.
+    pub hold: Option<u8>,
+}
+
+/// The most pcs that can be made from `hold` and `queue`, with how to make each.
+///
+/// With `env.can_hold`, each pc can use the held piece and leave another one behind; an empty hold is filled
+/// from the queue. Without it, pcs take the queue as given and `hold` is never used.
+///
+/// Plans making the same number of pcs are broken deterministically:
+/// at each step the first plan reached wins, going through positions in queue order and holds in byte order,
+/// then the empty hold. Between the states a best plan can end in, `env.foresight` picks the leftover most
+/// likely to start another pc; without it, or on a tie, the one that used the fewest pieces and then the
+/// lowest hold wins.
+///
+/// Once `env.state.budget` runs out, the best plan among the positions already covered is returned.
+#[must_use]
 pub fn max_pcs_in_queue(
+    hold: Option<u8>,
     queue: Queue,
     env: &Environment,
     pcs: &Map<Queue, History>,
-) -> (usize, Vec<History>) {
+) -> Plan {
     let maxn = pcs.iter().map(|x| x.0.len()).max().unwrap_or_default();
 
-    let mut dp: HashMap<(usize, u8), (usize, Option<(usize, u8)>, Option<Queue>)> = HashMap::new();
+    // 255 is the empty hold; with hold, that's the same as holding the current piece
+    let settle = |(i, hold): (usize, u8)| {
+        if env.can_hold && hold == 255 && i < queue.len() {
+            (i + 1, queue.get(i))
+        } else {
+            (i, hold)
+        }
+    };
+
+    // state -> (pcs so far, previous state, (the pc's queue, where it left the queue and hold))
+    let mut dp: HashMap<(usize, u8), (usize, Option<(usize, u8)>, Option<(Queue, (usize, u8))>)> =
+        HashMap::new();
 
-    dp.insert((1, queue.get(0)), (0, None, None));
+    dp.insert(settle((0, hold.unwrap_or(255))), (0, None, None));
 
-    for i in 1..queue.len() {
-        let mut reachable_holds: Vec<u8> = env.state.bag.pieces().collect::<Vec<_>>();
-        reachable_holds.push(255);
+    let mut reachable_holds: Vec<u8> = env.state.bag.pieces().collect();
+    reachable_holds.push(255);
+
+    for i in 0..=queue.len() {
+        // every state reached so far is still a valid plan
+        if env.state.budget.expired() {
+            break;
+        }
 
         for &hold in &reachable_holds {
             let current_state = (i, hold);
             if let Some(cdp) = dp.get(&current_state).copied() {
-                for pieces_used in 1..=std::cmp::min(queue.len() - i, maxn) {
-                    let mut pcq = Queue::new();
-                    pcq.push(hold);
-                    pcq.extend(queue.slice(i..i + pieces_used));
-
-                    let saves = get_pc_saves(pcq, pcs);
+                // with hold, the held piece can make a pc by itself
+                let fewest = usize::from(!env.can_hold || hold == 255);
+                for pieces_used in fewest..=std::cmp::min(queue.len() - i, maxn) {
+                    let pieces = queue.slice(i..i + pieces_used);
+
+                    // the piece left in hold after each pc, and the pc's queue
+                    let saves = if env.can_hold {
+                        let mut pcq = Queue::new();
+                        pcq.push(hold);
+                        pcq.extend(pieces);
+                        get_pc_saves(pcq, pcs)
+                    } else if pcs.contains_key(&pieces) {
+                        Map::from([(hold, pieces)])
+                    } else {
+                        Map::new()
+                    };
 
                     for (save, v) in saves {
-                        let next_state = (i + pieces_used, save);
+                        let next_state = settle((i + pieces_used, save));
                         let new_score = cdp.0 + 1;
 
                         if !dp.contains_key(&next_state)
                             || new_score > dp.get(&next_state).unwrap().0
                         {
-                            dp.insert(next_state, (new_score, Some(current_state), Some(v)));
+                            let after = (i + pieces_used, save);
+                            dp.insert(
+                                next_state,
+                                (new_score, Some(current_state), Some((v, after))),
+                            );
                         }
                     }
                 }
@@ -226,39 +343,102 @@ pub fn max_pcs_in_queue(
         }
     }
 
-    let mut max_score = 0;
-    let mut best_state = None;
-    for (&state, &(score, ..)) in &dp {
-        if score > max_score {
-            max_score = score;
-            best_state = Some(state);
-        }
+    let max_score = dp.values().map(|x| x.0).max().unwrap_or_default();
+    if max_score == 0 {
+        return Plan {
+            pcs: vec![],
+            hold,
+            rest: queue,
+        };
     }
 
-    if max_score == 0 || best_state.is_none() {
-        return (0, vec![]);
-    }
+    let mut ties: Vec<(usize, u8)> = dp
+        .iter()
+        .filter(|(_, x)| x.0 == max_score)
+        .map(|(&state, _)| state)
+        .collect();
+    ties.sort_unstable();
+
+    let best_state = if ties.len() > 1 && env.foresight > 0 && !env.state.budget.expired() {
+        most_likely_leftover(ties, hold, queue, maxn, env, pcs)
+    } else {
+        ties.first().copied()
+    };
 
     let mut rev = Vec::new();
     let mut current_state = best_state;
 
     while let Some(c) = current_state {
-        if let Some(&(_, prev, Some(ref t))) = dp.get(&c) {
-            rev.push(*t);
+        if let Some(&(_, prev, Some(step))) = dp.get(&c) {
+            rev.push(step);
             current_state = prev;
         } else {
             break;
         }
     }
 
-    let history: Vec<History> = rev
-        .iter()
-        .filter_map(|x| pcs.get(x))
-        .cloned()
-        .rev()
-        .collect();
+    let (end, last_hold) = rev.first().map_or((0, 255), |&(_, after)| after);
+    let occupied = |h: u8| (h != 255).then_some(h);
+
+    Plan {
+        pcs: rev
+            .iter()
+            .rev()
+            .filter_map(|(q, (_, h))| {
+                Some(PlannedPc {
+                    history: pcs.get(q)?.clone(),
+                    hold: occupied(*h),
+                })
+            })
+            .collect(),
+        hold: occupied(last_hold),
+        rest: queue.slice(end..),
+    }
+}
+
+/// The tied end state whose leftover pieces are most likely to start another pc; earlier states win ties.
+fn most_likely_leftover(
+    ties: Vec<(usize, u8)>,
+    hold: Option<u8>,
+    queue: Queue,
+    maxn: usize,
+    env: &Environment,
+    pcs: &Map<Queue, History>,
+) -> Option<(usize, u8)> {
+    // the pieces after the vision are only known up to the randomizer,
+    // so keep whatever leftover is most likely to start another pc
+    let seen: Vec<u8> = hold.into_iter().chain(queue).collect();
+    let continuations = env.state.randomizer.guess(&seen, env.foresight);
+
+    ties.into_iter()
+        .map(|(i, hold)| {
+            let leftover: Queue = std::iter::once(hold)
+                .filter(|&h| h != 255 && env.can_hold)
+                .chain(queue.slice(i..))
+                .collect();
+            let chance: f64 = continuations
+                .iter()
+                .filter(|(rest, _)| has_pc(leftover + *rest, maxn, env.can_hold, pcs))
+                .map(|(_, p)| p)
+                .sum();
+            ((i, hold), chance)
+        })
+        .fold(None, |best: Option<((usize, u8), f64)>, x| match best {
+            Some(b) if b.1 >= x.1 => Some(b),
+            _ => Some(x),
+        })
+        .map(|x| x.0)
+}
+
+/// Whether some prefix of `queue` makes a pc; with hold, it also has to leave a piece to save.
+fn has_pc(queue: Queue, maxn: usize, can_hold: bool, pcs: &Map<Queue, History>) -> bool {
+    if !can_hold {
+        return (1..=std::cmp::min(queue.len(), maxn))
+            .any(|k| pcs.contains_key(&queue.slice(0..k)));
+    }
 
-    (max_score, history)
+    (2..=std::cmp::min(queue.len(), maxn + 1))
+        .any(|k| !get_pc_saves(queue.slice(0..k), pcs).is_empty())
 }
 
 #[must_use] 
@@ -285,3 +465,241 @@ pub fn get_pc_saves(queue: Queue, pcs: &Map<Queue, History>) -> Map<u8, Queue> {
 
     saves
 }
+
+/// How often a PC can be reached once the rest of the queue is revealed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Percent {
+    /// Probability of at least one PC, weighted by the randomizer.
+    pub chance: f64,
+    pub successes: usize,
+    pub total: usize,
+    /// Continuations (excluding the known pieces) that can't PC.
+    pub fails: Vec<Queue>,
+}
+
+/// Like sfinder's `percent`: extends `hold` + `queue` to `len` pieces in every way `randomizer` allows
+/// and checks which of them contain a PC.
+///
+/// The known pieces are assumed to start at the beginning of the game.
+#[must_use]
+pub fn pc_percent(
+    hold: Option<u8>,
+    queue: Queue,
+    randomizer: &impl Randomizer,
+    len: usize,
+    env: &Environment,
+    pcs: &Map<Queue, History>,
+) -> Percent {
+    let known: Queue = hold.into_iter().chain(queue).collect();
+
+    let mut percent = Percent {
+        chance: 0.0,
+        successes: 0,
+        total: 0,
+        fails: vec![],
+    };
+
+    let seen: Vec<u8> = known.into_iter().collect();
+    for (rest, p) in randomizer.continuations(&seen, len.saturating_sub(known.len())) {
+        percent.total += 1;
+        let plan = max_pcs_in_queue(hold, queue + rest, env, pcs);
+        if plan.pcs.is_empty() {
+            percent.fails.push(rest);
+        } else {
+            percent.successes += 1;
+            percent.chance += p;
+        }
+    }
+
+    percent
+}
+
+/// Finds every way to perfect clear `board` using `hold` and `queue` without stacking above `height`.
+///
+/// Unlike [`generate_all_pc_queues`], the board doesn't have to be empty. Each solution lists the
+/// pieces in the order they are placed; holds are implied by that order. Shorter solutions come first.
+/// Once `env.state.budget` runs out, only the solutions found so far are returned.
+#[must_use]
+pub fn solve_pc<const W: usize>(
+    board: Board<W>,
+    hold: Option<u8>,
+    queue: Queue,
+    height: usize,
+    env: &Environment,
+) -> Vec<History> {
+    let mut solutions = vec![];
+    let mut dead = HashSet::new();
+    let mut path = History(smallvec![]);
+
+    if board.height() <= height {
+        solve_pc_from(
+            board,
+            hold,
+            queue,
+            0,
+            height,
+            env,
+            &mut path,
+            &mut dead,
+            &mut solutions,
+            false,
+        );
+    }
+
+    solutions.sort_by_key(|h| h.0.len());
+    solutions
+}
+
+/// Like [`solve_pc`], but stops at the first solution, using as few pieces from the front of `queue`
+/// as it can.
+///
+/// Returns `None` if there is no solution, or none was found before `env.state.budget` ran out.
+#[must_use]
+pub fn first_pc<const W: usize>(
+    board: Board<W>,
+    hold: Option<u8>,
+    queue: Queue,
+    height: usize,
+    env: &Environment,
+) -> Option<History> {
+    if board.height() > height {
+        return None;
+    }
+
+    let mut solutions = vec![];
+    let mut path = History(smallvec![]);
+
+    // any solution from a shorter prefix uses fewer pieces, so those go first
+    for len in 0..=queue.len() {
+        if env.state.budget.expired() {
+            break;
+        }
+
+        // states that failed with fewer pieces may still work with more
+        let mut dead = HashSet::new();
+        solve_pc_from(
+            board,
+            hold,
+            queue.slice(..len),
+            0,
+            height,
+            env,
+            &mut path,
+            &mut dead,
+            &mut solutions,
+            true,
+        );
+
+        if let Some(s) = solutions.pop() {
+            return Some(s);
+        }
+    }
+
+    None
+}
+
+/// Whether `left` minos, in pieces whose sizes are all multiples of `step`, could fill every empty cell
+/// of the rows a PC no taller than `height` clears.
+///
+/// Every row that's already there still has to be completed, and whole rows take a multiple of `step`.
+fn can_fill<const W: usize>(board: Board<W>, left: usize, step: usize, height: usize) -> bool {
+    let minos = board.num_minos() as usize;
+    (board.height()..=height).any(|rows| {
+        let empty = rows * W - minos;
+        // without any pieces, only an already empty board works
+        empty <= left && empty.checked_rem(step).unwrap_or(empty) == 0
+    })
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Returns whether any PC was found below this state. With `first`, stops as soon as one is.
+#[allow(clippy::too_many_arguments)]
+fn solve_pc_from<const W: usize>(
+    board: Board<W>,
+    hold: Option<u8>,
+    queue: Queue,
+    ptr: usize,
+    height: usize,
+    env: &Environment,
+    path: &mut History,
+    dead: &mut HashSet<(Board<W>, Option<u8>, usize)>,
+    solutions: &mut Vec<History>,
+    first: bool,
+) -> bool {
+    if dead.contains(&(board, hold, ptr)) || env.state.budget.expired() {
+        return false;
+    }
+
+    // the held piece only counts if it can be swapped back in
+    let (left, step) = queue
+        .slice(ptr..)
+        .into_iter()
+        .chain(hold.filter(|_| env.can_hold))
+        .map(|p| env.state.bag.minos(p).unwrap_or_default())
+        .fold((0, 0), |(left, step), m| (left + m, gcd(step, m)));
+    if !can_fill(board, left, step, height) {
+        dead.insert((board, hold, ptr));
+        return false;
+    }
+
+    // (piece to place, hold afterwards, next queue position)
+    let mut options = vec![];
+    if ptr < queue.len() {
+        options.push((queue.get(ptr), hold, ptr + 1));
+    }
+    if env.can_hold {
+        match hold {
+            Some(h) if ptr < queue.len() => options.push((h, Some(queue.get(ptr)), ptr + 1)),
+            Some(h) => options.push((h, None, ptr)),
+            None if ptr + 1 < queue.len() => {
+                options.push((queue.get(ptr + 1), Some(queue.get(ptr)), ptr + 2));
+            }
+            None => {}
+        }
+    }
+
+    // swapping with an identical piece changes nothing
+    options.dedup();
+
+    let mut found = false;
+    for (piece, next_hold, next_ptr) in options {
+        for (placement, f) in board.get_next_boards(piece, env) {
+            if placement.board.height() > height {
+                continue;
+            }
+
+            path.0.push(Pair(piece, f));
+            if placement.pc {
+                solutions.push(path.clone());
+                found = true;
+            } else {
+                found |= solve_pc_from(
+                    placement.board,
+                    next_hold,
+                    queue,
+                    next_ptr,
+                    height,
+                    env,
+                    path,
+                    dead,
+                    solutions,
+                    first,
+                );
+            }
+            path.0.pop();
+
+            if found && first {
+                return true;
+            }
+        }
+    }
+
+    if !found {
+        dead.insert((board, hold, ptr));
+    }
+
+    found
+}
@@ -37,38 +37,44 @@ impl Piece {
     }
 
     /// Returns the smallest possible sequence of inputs that can take this piece from its spawn location to its current location.
+    ///
+    /// The piece is assumed to be hard dropped after the inputs, so `self` should be a resting placement.
+    /// The result carries the spin the placement scores under `env`'s spin rule. Ties are broken by the order of `env.keyboard()`. Returns `None` if the placement can't be reached.
     #[must_use]
-    pub fn find(&self, board: Board, env: &Environment) -> Option<Finesse> {
-        let i = Input::new(board, self.name, env);
+    pub fn find<const W: usize>(&self, board: Board<W>, env: &Environment) -> Option<Finesse> {
+        let spawn = Input::new(board, self.name, env);
 
+        let keyboard = env.keyboard();
         let mut queue = VecDeque::new();
         let mut visited = HashSet::new();
 
-        queue.push_front(Finesse::new());
+        visited.insert(spawn.fingerprint());
+        queue.push_back((spawn, Finesse::new()));
 
-        while let Some(f) = queue.pop_back() {
-            if visited.contains(&i.place(false)) {
-                continue;
+        while let Some((i, f)) = queue.pop_front() {
+            let mut dropped = i;
+            dropped.sonic_drop();
+            if dropped.piece == *self && dropped.is_valid() {
+                return Some(f.with_spin(dropped.spin()));
             }
-            visited.insert(i.place(false));
 
-            if i.piece == *self {
-                return Some(f);
+            if f.len >= Finesse::MAX_LEN {
+                continue;
             }
 
-            for extra_key in env.keyboard() {
-                let mut i = i;
-                i.apply(f);
-                i.apply(Finesse::with(&[extra_key]));
-                if i.is_valid() {
+            for &key in &keyboard {
+                let mut next = i;
+                next.send(key);
+
+                if visited.insert(next.fingerprint()) {
                     let mut f = f;
-                    f.push(extra_key);
-                    queue.push_front(f);
+                    f.push(key);
+                    queue.push_back((next, f));
                 }
             }
         }
 
-        todo!()
+        None
     }
 }
 
@@ -80,6 +86,8 @@ pub struct Queue {
 
 impl Queue {
     pub const N: usize = 4;
+    /// Most pieces a queue can hold.
+    pub const CAPACITY: usize = Self::N * 16;
     #[must_use]
     pub fn new() -> Self {
         Self {
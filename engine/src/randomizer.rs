@@ -1,13 +1,34 @@
+use std::{collections::VecDeque, fmt::Display, ops::RangeInclusive, str::FromStr};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rustc_hash::FxHashMap;
+use smallvec::{SmallVec, smallvec};
+
+use crate::{
+    common::UnknownVariant,
+    file::{
+        piece::Bag,
+        randomizer::{Custom, CustomParseErr},
+    },
+    pc::Map,
+    piece::Queue,
+};
+
 pub struct Rng {
     pub seed: i32,
-    last_generated: Option<usize>,
-    bagid: usize,
-    extrabag: Vec<u8>,
+    pub(crate) last_generated: Option<usize>,
+    pub(crate) bagid: usize,
+    pub(crate) extrabag: Vec<u8>,
+    /// Pieces left in the current bag, for randomizers that deal one piece at a time.
+    pub(crate) bag: Vec<u8>,
+    /// Pieces dealt so far, most recent first.
+    pub(crate) history: VecDeque<u8>,
 }
 
 impl Rng {
     #[must_use]
     pub fn new(mut seed: i32) -> Self {
+        seed %= 2_147_483_647;
         if seed <= 0 {
             seed += 2_147_483_646;
         }
@@ -16,15 +37,74 @@ impl Rng {
             last_generated: None,
             bagid: 0,
             extrabag: vec![],
+            bag: vec![],
+            history: VecDeque::new(),
         }
     }
 
     pub const BAG: [u8; 7] = [b'Z', b'L', b'O', b'S', b'I', b'J', b'T'];
 
+    /// Largest seed that [`Rng::new`] keeps as is; every seed is equivalent to one in `1..=MAX_SEED`.
+    pub const MAX_SEED: i32 = 2_147_483_646;
+
+    /// Every piece this seed deals on `randomizer`, from the start of the game.
+    pub fn pieces<R: Randomizer>(seed: i32, randomizer: R) -> impl Iterator<Item = u8> {
+        let mut rng = Self::new(seed);
+        std::iter::repeat_with(move || rng.next_item(&randomizer)).flatten()
+    }
+
+    /// Whether this seed deals `observed` as the first pieces of the game.
+    #[must_use]
+    pub fn deals<R: Randomizer + Clone>(seed: i32, randomizer: &R, observed: &[u8]) -> bool {
+        Self::pieces(seed, randomizer.clone())
+            .zip(observed)
+            .all(|(a, &b)| a == b)
+    }
+
+    /// The smallest seeds in `seeds` (at most `limit` of them) that deal `observed` from the start of
+    /// the game.
+    ///
+    /// Searches in parallel; pass `1..=MAX_SEED` to cover every seed. The fewer pieces observed, the more
+    /// seeds match; it takes about three bags to pin down a 7-bag seed.
+    #[must_use]
+    pub fn recover<R: Randomizer + Clone + Sync>(
+        observed: &[u8],
+        randomizer: &R,
+        seeds: RangeInclusive<i32>,
+        limit: usize,
+    ) -> Vec<i32> {
+        const CHUNK: i32 = 1 << 16;
+
+        let last = *seeds.end();
+        let starts: Vec<i32> = seeds.step_by(CHUNK as usize).collect();
+        let mut found = vec![];
+
+        // go through the chunks a batch at a time so the search can stop early
+        // and still return the smallest seeds
+        for batch in starts.chunks(rayon::current_num_threads() * 16) {
+            let hits: Vec<i32> = batch
+                .par_iter()
+                .flat_map_iter(|&start| {
+                    let end = start.saturating_add(CHUNK - 1).min(last);
+                    (start..=end).filter(|&seed| Self::deals(seed, randomizer, observed))
+                })
+                .collect();
+
+            found.extend(hits);
+            if found.len() >= limit {
+                found.truncate(limit);
+                break;
+            }
+        }
+
+        found
+    }
+
     #[must_use]
-    #[allow(clippy::should_implement_trait)]
+    #[allow(clippy::should_implement_trait, clippy::cast_possible_truncation)]
     pub fn next(&mut self) -> i32 {
-        self.seed = 16_807 * self.seed % 2_147_483_647;
+        // the product needs up to 46 bits; the result always fits back in an i32
+        self.seed = (16_807 * i64::from(self.seed) % 2_147_483_647) as i32;
         self.seed
     }
 
@@ -52,102 +132,510 @@ impl Rng {
             return array;
         }
 
-        for i in array.len()..0 {
-            let r = self.next_float() * (i + 1) as f64;
-            array.swap(i, r as usize);
+        for i in (1..array.len()).rev() {
+            let r = (self.next_float() * (i + 1) as f64).floor() as usize;
+            array.swap(i, r);
         }
 
         array
     }
 
+    pub fn next_item<R: Randomizer + ?Sized>(&mut self, randomizer: &R) -> Vec<u8> {
+        randomizer.deal(self)
+    }
+
+    // given a `hint` and `randomizer`,
+    // determine all possible sequences that could come *after*
+    // the `hint` while placying on this `randomizer`
+    // for example if hint is `ZSSIJLO`, we know either `TZ` or `ZT` must come after it
+    // on Bag7 randomizer.
+    #[must_use]
+    pub fn guess<R: Randomizer + ?Sized>(
+        hint: &[char],
+        len: usize,
+        randomizer: &R,
+    ) -> Vec<(Vec<char>, f64)> {
+        let Ok(hint) = hint
+            .iter()
+            .map(|&c| u8::try_from(c))
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            return vec![];
+        };
+
+        randomizer
+            .guess(&hint, len)
+            .into_iter()
+            .map(|(q, p)| (q.into_iter().map(char::from).collect(), p))
+            .collect()
+    }
+}
+
+/// Deals pieces, both for real (from an [`Rng`]) and as a distribution over what can come next.
+pub trait Randomizer {
+    /// Every piece this randomizer can deal; [`Draw`] counts are indexed into this.
+    fn pieces(&self) -> &[u8];
+
+    /// Deals the next pieces; a whole bag at once, or a single piece.
+    fn deal(&self, rng: &mut Rng) -> Vec<u8>;
+
+    /// Every piece that can come next from `state`, as an index into [`Randomizer::pieces`],
+    /// with its probability and the state after it.
+    fn next(&self, state: &Draw) -> Vec<(usize, f64, Draw)>;
+
+    /// How many pieces can be drawn before the randomizer starts repeating its states.
+    fn period(&self) -> usize;
+
+    /// Every way this randomizer can continue `seen` with `len` more pieces, and how likely each is.
+    ///
+    /// `seen` is assumed to start at the beginning of the game. The result is sorted by queue.
+    #[must_use]
+    fn continuations(&self, seen: &[u8], len: usize) -> Vec<(Queue, f64)> {
+        extend(
+            self,
+            FxHashMap::from_iter([(Draw::default(), 1.0)]),
+            seen,
+            len,
+        )
+    }
+
+    /// Like [`Randomizer::continuations`], but `seen` may start anywhere in the game.
+    ///
+    /// Every position within [`Randomizer::period`] pieces of the start is taken as equally likely,
+    /// and `seen` narrows them down to the ones that could have produced it.
+    #[must_use]
+    fn guess(&self, seen: &[u8], len: usize) -> Vec<(Queue, f64)> {
+        let period = self.period();
+        #[allow(clippy::cast_precision_loss)]
+        let weight = 1.0 / period as f64;
+
+        let mut prefix: FxHashMap<Draw, f64> = FxHashMap::from_iter([(Draw::default(), 1.0)]);
+        let mut states: FxHashMap<Draw, f64> = FxHashMap::default();
+        for _ in 0..period {
+            for (s, &p) in &prefix {
+                *states.entry(s.clone()).or_insert(0.0) += p * weight;
+            }
+
+            // draw one piece nobody saw
+            let mut next = FxHashMap::default();
+            for (s, p) in prefix {
+                for (_, q, n) in self.next(&s) {
+                    *next.entry(n).or_insert(0.0) += p * q;
+                }
+            }
+            prefix = next;
+        }
+
+        extend(self, states, seen, len)
+    }
+}
+
+/// Conditions `states` on drawing `seen`, then branches `len` pieces past it.
+fn extend<R: Randomizer + ?Sized>(
+    randomizer: &R,
+    mut states: FxHashMap<Draw, f64>,
+    seen: &[u8],
+    len: usize,
+) -> Vec<(Queue, f64)> {
+    let pieces = randomizer.pieces();
+
+    for &piece in seen {
+        let Some(idx) = pieces.iter().position(|&x| x == piece) else {
+            return vec![];
+        };
+
+        let mut next = FxHashMap::default();
+        for (s, p) in states {
+            for (i, q, n) in randomizer.next(&s) {
+                if i == idx {
+                    *next.entry(n).or_insert(0.0) += p * q;
+                }
+            }
+        }
+        states = next;
+    }
+
+    let total: f64 = states.values().sum();
+    if total == 0.0 {
+        return vec![];
+    }
+
+    let mut branches: FxHashMap<(Queue, Draw), f64> = states
+        .into_iter()
+        .map(|(s, p)| ((Queue::new(), s), p / total))
+        .collect();
+
+    for _ in 0..len {
+        let mut next = FxHashMap::default();
+        for ((q, s), p) in branches {
+            for (i, r, n) in randomizer.next(&s) {
+                *next.entry((q + pieces[i], n)).or_insert(0.0) += p * r;
+            }
+        }
+        branches = next;
+    }
+
+    let mut out: Map<Queue, f64> = Map::new();
+    for ((q, _), p) in branches {
+        *out.entry(q).or_insert(0.0) += p;
+    }
+
+    out.into_iter().collect()
+}
+
+/// Piece counts, indexed like [`Randomizer::pieces`].
+pub type Counts = SmallVec<[u8; 8]>;
+
+/// What a randomizer remembers between pieces.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Draw {
+    /// Pieces left in the current bag.
+    pub bag: Counts,
+    /// Pieces left in the pool extras are drawn from.
+    pub extra: Counts,
+    pub bagid: usize,
+    /// Pieces dealt so far, most recent first, as far back as the randomizer looks.
+    pub history: SmallVec<[usize; 4]>,
+}
+
+impl Draw {
+    /// Every way to take one piece out of `bag`.
+    pub fn take(bag: &Counts) -> impl Iterator<Item = (usize, f64, Counts)> + '_ {
+        let total: u8 = bag.iter().sum();
+        (0..bag.len()).filter(|&i| bag[i] > 0).map(move |i| {
+            let mut b = bag.clone();
+            b[i] -= 1;
+            (i, f64::from(bag[i]) / f64::from(total), b)
+        })
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bag.iter().all(|&x| x == 0)
+    }
+
+    /// A new bag with `copies` of each of `n` pieces, starting the next bag id.
+    #[must_use]
+    pub fn refill(&self, n: usize, copies: u8) -> Self {
+        Self {
+            bag: smallvec![copies; n],
+            bagid: self.bagid + 1,
+            ..self.clone()
+        }
+    }
+
+    /// Adds `count` extras to each state's bag, each one any piece with equal chance.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn random_extras(states: Vec<(Self, f64)>, count: usize) -> Vec<(Self, f64)> {
+        let mut out = states;
+        for _ in 0..count {
+            out = out
+                .into_iter()
+                .flat_map(|(s, p)| {
+                    let n = s.bag.len();
+                    (0..n).map(move |i| {
+                        let mut s = s.clone();
+                        s.bag[i] += 1;
+                        (s, p / n as f64)
+                    })
+                })
+                .collect();
+        }
+        out
+    }
+
+    /// Adds `count` extras to each state's bag from its pool, which gets one of each piece when it runs short.
+    #[must_use]
+    pub fn pooled_extras(states: Vec<(Self, f64)>, count: usize) -> Vec<(Self, f64)> {
+        let mut out: Vec<(Self, f64)> = states
+            .into_iter()
+            .map(|(mut s, p)| {
+                if s.extra.iter().map(|&x| usize::from(x)).sum::<usize>() < count {
+                    s.extra = smallvec![1; s.bag.len()];
+                }
+                (s, p)
+            })
+            .collect();
+
+        for _ in 0..count {
+            out = out
+                .into_iter()
+                .flat_map(|(s, p)| {
+                    Draw::take(&s.extra)
+                        .map(|(i, q, extra)| {
+                            let mut bag = s.bag.clone();
+                            bag[i] += 1;
+                            (
+                                Self {
+                                    bag,
+                                    extra,
+                                    ..s.clone()
+                                },
+                                p * q,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+        }
+        out
+    }
+
+    /// Takes one piece out of each state's bag.
+    #[must_use]
+    pub fn take_from_bag(states: Vec<(Self, f64)>) -> Vec<(usize, f64, Self)> {
+        states
+            .into_iter()
+            .flat_map(|(s, p)| {
+                Self::take(&s.bag)
+                    .map(|(i, q, bag)| (i, p * q, Self { bag, ..s.clone() }))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Remembers `piece`, keeping the `len` most recent.
+    #[must_use]
+    pub fn remember(mut self, piece: usize, len: usize) -> Self {
+        self.history.insert(0, piece);
+        self.history.truncate(len);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Builtin {
+    #[default]
+    Bag7,
+    Bag14,
+    Bag7P1,
+    Bag7P2,
+    Bag7PX,
+    Classic,
+    Pairs,
+    TotalMayhem,
+}
+
+impl Display for Builtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Bag7 => "7-bag",
+                Self::Bag14 => "14-bag",
+                Self::Bag7P1 => "7+1-bag",
+                Self::Bag7P2 => "7+2-bag",
+                Self::Bag7PX => "7+x-bag",
+                Self::Classic => "classic",
+                Self::Pairs => "pairs",
+                Self::TotalMayhem => "total-mayhem",
+            }
+        )
+    }
+}
+
+impl FromStr for Builtin {
+    type Err = UnknownVariant;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "7-bag" | "7bag" | "bag7" => Ok(Self::Bag7),
+            "14-bag" | "14bag" | "bag14" => Ok(Self::Bag14),
+            "7+1-bag" | "7+1" => Ok(Self::Bag7P1),
+            "7+2-bag" | "7+2" => Ok(Self::Bag7P2),
+            "7+x-bag" | "7+x" => Ok(Self::Bag7PX),
+            "classic" => Ok(Self::Classic),
+            "pairs" => Ok(Self::Pairs),
+            "total-mayhem" | "mayhem" | "random" => Ok(Self::TotalMayhem),
+            c => Err(UnknownVariant(c.to_string())),
+        }
+    }
+}
+
+impl Randomizer for Builtin {
+    fn pieces(&self) -> &[u8] {
+        &Rng::BAG
+    }
+
     #[allow(const_item_mutation)]
     #[allow(
         clippy::cast_precision_loss,
         clippy::cast_possible_truncation,
         clippy::cast_sign_loss
     )]
-    pub fn next_item(&mut self, randomizer: Randomizer) -> Vec<u8> {
-        match randomizer {
-            Randomizer::Bag7 => self.shuffle_array(&mut Self::BAG).to_vec(),
-            Randomizer::Bag14 => self
+    fn deal(&self, rng: &mut Rng) -> Vec<u8> {
+        match self {
+            Self::Bag7 => rng.shuffle_array(&mut Rng::BAG).to_vec(),
+            Self::Bag14 => rng
                 .shuffle_array(&mut [
                     b'Z', b'L', b'O', b'S', b'I', b'J', b'T', b'Z', b'L', b'O', b'S', b'I', b'J',
                     b'T',
                 ])
                 .to_vec(),
-            Randomizer::Classic => {
-                let tet = Self::BAG;
+            Self::Classic => {
+                let tet = Rng::BAG;
 
-                let mut idx = (self.next_float() * ((tet.len() + 1) as f64)).floor() as usize;
-                if Some(idx) == self.last_generated || idx >= tet.len() {
-                    idx = (self.next_float() * (tet.len() as f64)).floor() as usize;
+                let mut idx = (rng.next_float() * ((tet.len() + 1) as f64)).floor() as usize;
+                if Some(idx) == rng.last_generated || idx >= tet.len() {
+                    idx = (rng.next_float() * (tet.len() as f64)).floor() as usize;
                 }
 
-                self.last_generated = Some(idx);
+                rng.last_generated = Some(idx);
                 vec![tet[idx]]
             }
-            Randomizer::Pairs => {
+            Self::Pairs => {
                 let mut z = [b'Z', b'L', b'O', b'S', b'I', b'J', b'T'];
-                let s = self.shuffle_array(&mut z);
+                let s = rng.shuffle_array(&mut z);
                 let mut pairs = [s[0], s[0], s[0], s[1], s[1], s[1]];
-                self.shuffle_array(&mut pairs).to_vec()
+                rng.shuffle_array(&mut pairs).to_vec()
             }
-            Randomizer::Bag7P1 => {
-                let t: &[u8] = &Self::BAG;
-                let extra = t[(self.next_float() * 7.0).floor() as usize];
+            Self::Bag7P1 => {
+                let t: &[u8] = &Rng::BAG;
+                let extra = t[(rng.next_float() * 7.0).floor() as usize];
                 let mut t2 = [t, &[extra]].concat();
-                let bag = self.shuffle_array(&mut t2);
+                let bag = rng.shuffle_array(&mut t2);
                 bag.to_vec()
             }
-            Randomizer::Bag7P2 => {
-                let t: &[u8] = &Self::BAG;
-                let extr1 = t[(self.next_float() * 7.0).floor() as usize];
-                let extr2 = t[(self.next_float() * 7.0).floor() as usize];
+            Self::Bag7P2 => {
+                let t: &[u8] = &Rng::BAG;
+                let extr1 = t[(rng.next_float() * 7.0).floor() as usize];
+                let extr2 = t[(rng.next_float() * 7.0).floor() as usize];
 
                 let mut t2: Vec<u8> = [t, &[extr1, extr2]].concat();
-                let bag = self.shuffle_array(&mut t2);
+                let bag = rng.shuffle_array(&mut t2);
                 bag.to_vec()
             }
-            Randomizer::Bag7PX => {
+            Self::Bag7PX => {
                 let extra_piece_count = [3, 2, 1, 1];
                 let extra = extra_piece_count
-                    .get(self.bagid)
+                    .get(rng.bagid)
                     .copied()
                     .unwrap_or_default();
-                self.bagid += 1;
-                if self.extrabag.len() < extra {
-                    self.extrabag = self.shuffle_array(&mut Self::BAG).to_vec();
+                rng.bagid += 1;
+                if rng.extrabag.len() < extra {
+                    rng.extrabag = rng.shuffle_array(&mut Rng::BAG).to_vec();
                 }
 
-                self.shuffle_array(&mut [&Self::BAG, &self.extrabag[0..extra]].concat())
+                let extras: Vec<u8> = rng.extrabag.drain(..extra).collect();
+                rng.shuffle_array(&mut [&Rng::BAG, &extras[..]].concat())
                     .to_vec()
             }
-            Randomizer::TotalMayhem => {
-                vec![Self::BAG[(self.next_float() * Self::BAG.len() as f64).floor() as usize]]
+            Self::TotalMayhem => {
+                vec![Rng::BAG[(rng.next_float() * Rng::BAG.len() as f64).floor() as usize]]
             }
         }
     }
 
-    // given a `hint` and `randomizer`,
-    // determine all possible sequences that could come *after*
-    // the `hint` while placying on this `randomizer`
-    // for example if hint is `ZSSIJLO`, we know either `TZ` or `ZT` must come after it
-    // on Bag7 randomizer.
-    #[must_use]
-    pub fn guess(hint: &[char], len: usize, randomizer: Randomizer) -> Vec<Vec<char>> {
-        let _ = (hint, len, randomizer);
-        todo!();
+    fn next(&self, state: &Draw) -> Vec<(usize, f64, Draw)> {
+        match self {
+            Self::TotalMayhem => (0..7).map(|i| (i, 1.0 / 7.0, state.clone())).collect(),
+            Self::Classic => {
+                // roll 0..8; a repeat or the 8th value rerolls 0..7 once
+                let last = state.history.first().copied();
+                let reroll = if last.is_some() { 2.0 } else { 1.0 } / 8.0;
+                (0..7)
+                    .map(|i| {
+                        let direct = if Some(i) == last { 0.0 } else { 1.0 / 8.0 };
+                        (i, direct + reroll / 7.0, state.clone().remember(i, 1))
+                    })
+                    .collect()
+            }
+            _ if !state.is_empty() => Draw::take_from_bag(vec![(state.clone(), 1.0)]),
+            Self::Bag7 => Draw::take_from_bag(vec![(state.refill(7, 1), 1.0)]),
+            Self::Bag14 => Draw::take_from_bag(vec![(state.refill(7, 2), 1.0)]),
+            Self::Bag7P1 => {
+                Draw::take_from_bag(Draw::random_extras(vec![(state.refill(7, 1), 1.0)], 1))
+            }
+            Self::Bag7P2 => {
+                Draw::take_from_bag(Draw::random_extras(vec![(state.refill(7, 1), 1.0)], 2))
+            }
+            Self::Bag7PX => {
+                let count = [3, 2, 1, 1].get(state.bagid).copied().unwrap_or_default();
+                Draw::take_from_bag(Draw::pooled_extras(vec![(state.refill(7, 1), 1.0)], count))
+            }
+            Self::Pairs => {
+                let pairs = (0..49)
+                    .filter(|e| e / 7 != e % 7)
+                    .map(|e| {
+                        let mut s = state.refill(7, 0);
+                        s.bag[e / 7] = 3;
+                        s.bag[e % 7] = 3;
+                        (s, 1.0 / 42.0)
+                    })
+                    .collect();
+                Draw::take_from_bag(pairs)
+            }
+        }
+    }
+
+    /// For 7+X this runs through the bags with extras and one plain bag after them.
+    fn period(&self) -> usize {
+        match self {
+            Self::Bag7 => 7,
+            Self::Bag14 => 14,
+            Self::Bag7P1 => 8,
+            Self::Bag7P2 => 9,
+            Self::Bag7PX => 10 + 9 + 8 + 8 + 7,
+            Self::Classic => 2,
+            Self::Pairs => 6,
+            Self::TotalMayhem => 1,
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum Randomizer {
-    Bag7,
-    Bag14,
-    Bag7P1,
-    Bag7P2,
-    Bag7PX,
-    Classic,
-    Pairs,
-    TotalMayhem,
+/// Either kind of randomizer, so it can be picked at runtime.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum AnyRandomizer {
+    Builtin(Builtin),
+    Custom(Custom),
+}
+
+impl Default for AnyRandomizer {
+    fn default() -> Self {
+        Self::Builtin(Builtin::default())
+    }
+}
+
+impl AnyRandomizer {
+    /// A builtin by name, or else the custom randomizer in `data/{name}.rand` dealing pieces from `bag`.
+    pub fn load(name: &str, bag: &Bag) -> Result<Self, CustomParseErr> {
+        if let Ok(b) = name.parse() {
+            return Ok(Self::Builtin(b));
+        }
+
+        let s = std::fs::read_to_string(format!("data/{name}.rand"))
+            .map_err(|_| CustomParseErr::NotFound(name.to_string()))?;
+        Ok(Self::Custom(s.parse::<Custom>()?.with_bag(bag)?))
+    }
+}
+
+impl Randomizer for AnyRandomizer {
+    fn pieces(&self) -> &[u8] {
+        match self {
+            Self::Builtin(r) => r.pieces(),
+            Self::Custom(r) => r.pieces(),
+        }
+    }
+
+    fn deal(&self, rng: &mut Rng) -> Vec<u8> {
+        match self {
+            Self::Builtin(r) => r.deal(rng),
+            Self::Custom(r) => r.deal(rng),
+        }
+    }
+
+    fn next(&self, state: &Draw) -> Vec<(usize, f64, Draw)> {
+        match self {
+            Self::Builtin(r) => r.next(state),
+            Self::Custom(r) => r.next(state),
+        }
+    }
+
+    fn period(&self) -> usize {
+        match self {
+            Self::Builtin(r) => r.period(),
+            Self::Custom(r) => r.period(),
+        }
+    }
 }
@@ -1,191 +1,205 @@
-use std::{collections::VecDeque, hash::Hash};
-
-use crate::{board::Board, environment::Environment, input::Finesse};
-
-#[derive(Debug, Clone, Eq)]
-pub struct Node<'a> {
-    pub board: Board,
+use std::{cmp::Reverse, rc::Rc};
+
+use rustc_hash::FxHashMap;
+use smallvec::{SmallVec, smallvec};
+
+use crate::{
+    board::Board,
+    environment::Environment,
+    input::{Finesse, Pair, Placement},
+    pc::History,
+};
+
+/// A state of the combo search; `ptr` is how far into the queue it is.
+///
+/// Together with the starting hold, this fixes how many pieces have been placed, so it's all the search
+/// needs to remember a state by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Node<const W: usize = 4> {
+    pub board: Board<W>,
     pub hold: Option<u8>,
-    pub queue: &'a [u8],
-    pub prev: Option<Box<Self>>,
-    pub finesse: Finesse,
-    pub used: Option<u8>,
     pub ptr: usize,
 }
 
-#[must_use] 
-pub fn ren_bfs(state: &Node, env: &Environment) -> Vec<Vec<PathItem>> {
-    let mut queue: VecDeque<Node> = VecDeque::new();
-    let mut visited: Vec<Node> = Vec::new();
-
-    let ql = env.vision;
-
-    // todo: optimize for n > 0
-    // `n` represents the maximum amount of breaks we allow in a single path
-    // problem is our sample space increases exponentially as `n` increases
-    // when `n` is 0 we discard an extremely large amount of cases because they just kill you
-    // this usually isnt an issue given that combo tends to have very few breaks on a sensible `vision`
-    // but this does take an abundantly long time for poor `vision` or just actually evil queues that have many breaks
-    for n in 0..env.vision {
-        queue.push_front(state.clone());
-        visited.push(state.clone());
-
-        let mut p = vec![];
-        while !queue.is_empty() {
-            let current_node = queue.pop_back().unwrap();
-
-            // discard any segment that has more than wanted non-combo placements
-            if current_node.breaks() > n {
-                continue;
-            }
-
-            // only output finished paths
-            if current_node.ptr == ql {
-                p.push(current_node.path());
-            }
-
-            for neighbor in current_node.neighbors(env) {
-                visited.push(neighbor.clone());
-                queue.push_front(neighbor);
-            }
-        }
-
-        if !p.is_empty() {
-            return p;
-        }
-    }
+/// A combo path and where it leaves the board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ren<const W: usize = 4> {
+    pub history: History,
+    /// Placements that didn't clear a line.
+    pub breaks: usize,
+    pub board: Board<W>,
+}
 
-    vec![]
+/// The best way on from a state, kept in [`Search::arena`].
+#[derive(Clone, Copy, Debug)]
+struct Best<const W: usize> {
+    board: Board<W>,
+    placed: usize,
+    breaks: usize,
+    /// Whether the break limit cut off any path from here.
+    pruned: bool,
+    /// The first placement and the arena index of the state it leads to.
+    next: Option<(Pair, usize)>,
 }
 
-impl PartialEq for Node<'_> {
-    fn eq(&self, other: &Self) -> bool {
-        self.ptr == other.ptr && self.hold == other.hold && self.board == other.board
-    }
+struct Search<'a, 'e, const W: usize> {
+    queue: &'a [u8],
+    /// Whether the search started with a held piece.
+    held: bool,
+    env: &'a Environment<'e>,
+    /// Every state solved so far; `next` links make up the paths.
+    arena: Vec<Best<W>>,
+    /// Arena index for each state and number of breaks left.
+    memo: FxHashMap<(Node<W>, usize), usize>,
+    moves: FxHashMap<(Board<W>, u8), Rc<[(Placement<W>, Finesse)]>>,
 }
 
-impl Hash for Node<'_> {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.ptr.hash(state);
-        self.hold.hash(state);
-        self.board.hash(state);
+impl<const W: usize> Search<'_, '_, W> {
+    fn placed(&self, node: Node<W>) -> usize {
+        node.ptr + usize::from(self.held) - usize::from(node.hold.is_some())
     }
-}
 
-impl Node<'_> {
-    #[must_use] 
-    pub fn neighbors(self, env: &Environment) -> Vec<Self> {
-        let mut n = vec![];
-
-        if self.queue.is_empty() {
-            if let Some(h) = self.hold {
-                for (c, f) in self.board.get_next_boards(h, env) {
-                    n.push(Node {
-                        board: c,
-                        hold: None,
-                        queue: &self.queue[0..0],
-                        prev: Some(Box::new(self.clone())),
-                        finesse: f,
-                        used: Some(h),
-                        ptr: self.ptr + 1,
-                    });
+    /// (piece to place, hold afterwards, next queue position)
+    fn options(&self, node: Node<W>) -> SmallVec<[(u8, Option<u8>, usize); 3]> {
+        let Some(&current) = self.queue.get(node.ptr) else {
+            // the held piece is all that's left
+            return match node.hold {
+                Some(h) if self.env.can_hold => smallvec![(h, None, node.ptr)],
+                _ => smallvec![],
+            };
+        };
+
+        let mut options = smallvec![(current, node.hold, node.ptr + 1)];
+        if self.env.can_hold {
+            match node.hold {
+                // swapping with an identical piece changes nothing
+                Some(h) if h != current => options.push((h, Some(current), node.ptr + 1)),
+                Some(_) => {}
+                None => {
+                    if let Some(&next) = self.queue.get(node.ptr + 1) {
+                        options.push((next, Some(current), node.ptr + 2));
+                    }
                 }
-
-                return n;
             }
-            return n;
         }
 
-        // consume regularly
-        for (c, f) in self.board.get_next_boards(self.queue[0], env) {
-            n.push(Node {
-                board: c,
-                hold: self.hold,
-                queue: &self.queue[1..],
-                prev: Some(Box::new(self.clone())),
-                finesse: f,
-                used: Some(self.queue[0]),
-                ptr: self.ptr + 1,
-            });
-        }
-
-        if let Some(h) = self.hold {
-            for (c, f) in self.board.get_next_boards(h, env) {
-                n.push(Node {
-                    board: c,
-                    hold: Some(self.queue[0]),
-                    queue: &self.queue[1..],
-                    prev: Some(Box::new(self.clone())),
-                    finesse: f,
-                    used: Some(h),
-                    ptr: self.ptr + 1,
-                });
-            }
-        }
-
-        if let Some(t) = self.queue.get(1)
-            && self.hold.is_none()
-        {
-            for (c, f) in self.board.get_next_boards(*t, env) {
-                n.push(Node {
-                    board: c,
-                    hold: Some(self.queue[0]),
-                    queue: &self.queue[2..],
-                    prev: Some(Box::new(self.clone())),
-                    finesse: f,
-                    used: Some(self.queue[1]),
-                    ptr: self.ptr + 1,
-                });
-            }
-        }
-
-        n
+        options
     }
 
-    #[must_use] 
-    pub fn size(&self) -> usize {
-        1 + self.prev.as_ref().map_or(0, |x| x.size())
+    fn moves(&mut self, board: Board<W>, piece: u8) -> Rc<[(Placement<W>, Finesse)]> {
+        self.moves
+            .entry((board, piece))
+            .or_insert_with(|| board.get_next_boards(piece, self.env).into())
+            .clone()
     }
 
-    #[must_use] 
-    pub fn path(&self) -> Vec<PathItem> {
-        if self.prev.is_none() {
-            return vec![];
+    /// Arena index of the best way on from `node` with at most `limit` more breaks: the most pieces
+    /// placed, then the fewest breaks.
+    fn solve(&mut self, node: Node<W>, limit: usize) -> usize {
+        if let Some(&i) = self.memo.get(&(node, limit)) {
+            return i;
         }
 
-        [
-            self.prev.clone().unwrap().path(),
-            vec![PathItem(self.board, self.used.unwrap(), self.finesse)],
-        ]
-        .concat()
-    }
+        let mut best = Best {
+            board: node.board,
+            placed: 0,
+            breaks: 0,
+            pruned: false,
+            next: None,
+        };
+
+        if self.placed(node) < self.env.vision {
+            // past the budget, every state left is a dead end
+            if self.env.state.budget.expired() {
+                best.pruned = true;
+            } else {
+                for (piece, hold, ptr) in self.options(node) {
+                    for &(placement, finesse) in self.moves(node.board, piece).iter() {
+                        let broke = usize::from(placement.lines == 0);
+                        let Some(left) = limit.checked_sub(broke) else {
+                            best.pruned = true;
+                            continue;
+                        };
+
+                        let next = Node {
+                            board: placement.board,
+                            hold,
+                            ptr,
+                        };
+                        let i = self.solve(next, left);
+                        best.pruned |= self.arena[i].pruned;
+
+                        let placed = self.arena[i].placed + 1;
+                        let breaks = self.arena[i].breaks + broke;
+                        // the first path found wins ties
+                        if (placed, Reverse(breaks)) > (best.placed, Reverse(best.breaks)) {
+                            best.placed = placed;
+                            best.breaks = breaks;
+                            best.next = Some((Pair(piece, finesse), i));
+                        }
+                    }
+                }
+            }
+        }
 
-    #[must_use] 
-    pub fn path_small(&self) -> String {
-        self.path()
-            .into_iter()
-            .map(|x| format!("({} {})", x.1, x.2.short()))
-            .collect::<Vec<_>>()
-            .join(", ")
+        self.arena.push(best);
+        self.memo.insert((node, limit), self.arena.len() - 1);
+        self.arena.len() - 1
     }
+}
 
-    #[must_use] 
-    pub fn breaks(&self) -> usize {
-        if let Some(p) = &self.prev {
-            usize::from(self.board.num_minos() > p.board.num_minos()) + p.breaks()
-        } else {
-            0
+/// The best combo path through `queue` from `board` and `hold`, placing up to `env.vision` pieces.
+///
+/// Paths that place more pieces win, then those with fewer breaks; among equals, the current piece is
+/// tried before the held one and placements go in [`Board::get_next_boards`] order.
+///
+/// The search allows one more break at a time until every piece can be placed, and solves each state
+/// once per number of breaks left, whatever path reaches it. Once `env.state.budget` runs out, the rest
+/// of the search is treated as dead ends, so the best path found so far is returned.
+///
+/// Returns `None` if not a single piece can be placed.
+#[must_use]
+pub fn best_ren<const W: usize>(
+    board: Board<W>,
+    hold: Option<u8>,
+    queue: &[u8],
+    env: &Environment,
+) -> Option<Ren<W>> {
+    let mut search = Search {
+        queue,
+        held: hold.is_some(),
+        env,
+        arena: vec![],
+        memo: FxHashMap::default(),
+        moves: FxHashMap::default(),
+    };
+
+    let root = Node {
+        board,
+        hold,
+        ptr: 0,
+    };
+    let most = env.vision.min(queue.len() + usize::from(hold.is_some()));
+
+    let mut i = search.solve(root, 0);
+    for limit in 1..=most {
+        let best = search.arena[i];
+        if best.placed == most || !best.pruned || env.state.budget.expired() {
+            break;
         }
+        i = search.solve(root, limit);
     }
+    let breaks = search.arena[i].breaks;
 
-    #[must_use] 
-    pub fn non_pcs(&self) -> usize {
-        if let Some(p) = &self.prev {
-            usize::from(self.board.num_minos() != 0) + p.non_pcs()
-        } else {
-            0
-        }
+    let mut history = History(smallvec![]);
+    while let Some((pair, next)) = search.arena[i].next {
+        history.0.push(pair);
+        i = next;
     }
+
+    (!history.0.is_empty()).then(|| Ren {
+        history,
+        breaks,
+        board: search.arena[i].board,
+    })
 }
-#[derive(Copy, Clone, Debug)]
-pub struct PathItem(pub Board, pub u8, pub Finesse);
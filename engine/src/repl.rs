@@ -1,18 +1,31 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
+    fmt::Display,
     io::{BufRead, BufReader, Read, Write},
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
     thread::{self, JoinHandle},
+    time::Duration,
 };
 
+use smallvec::smallvec;
+
 use crate::{
-    environment::Environment,
+    board::Board,
+    budget::Budget,
+    environment::{Environment, PcsErr},
     file::{corners::Corners, kicks::Kicks, piece::Bag},
-    pc::{History, Map, max_pcs_in_queue},
+    input::Pair,
+    pc::{History, Map, Plan, first_pc, max_pcs_in_queue, pc_percent, solve_pc},
     piece::Queue,
+    randomizer::AnyRandomizer,
+    ren::best_ren,
+    sim::{Planner, Simulation},
+    spin::SpinRule,
 };
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -20,9 +33,123 @@ pub struct State {
     pub kicks: Kicks,
     pub bag: Bag,
     pub corners: Corners,
+    pub spin_rule: SpinRule,
+    /// Used to weigh what may come after the visible queue.
+    pub randomizer: AnyRandomizer,
     pub fingerprint: (String, String, String),
 
     pub pcs: HashMap<usize, Map<Queue, History>>,
+    /// The game being played through `start`, `push`, `play` and `next`.
+    pub session: Option<Session>,
+    /// Restarted for every line the repl answers.
+    pub budget: Budget,
+}
+
+/// A game fed to the engine one piece and one placement at a time.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Session {
+    pub flags: String,
+    /// Size of the pcs planned from an empty board.
+    pub n: usize,
+    pub foresight: usize,
+    pub board: Board,
+    pub hold: Option<u8>,
+    /// Revealed pieces that haven't been played, current piece first.
+    pub queue: VecDeque<u8>,
+    /// Placements left from the last plan; dropped once play diverges from it.
+    pub plan: VecDeque<Pair>,
+}
+
+impl Session {
+    #[must_use]
+    pub fn new(flags: &str, n: usize, foresight: usize) -> Self {
+        Self {
+            flags: flags.to_string(),
+            n,
+            foresight,
+            board: Board::empty(),
+            hold: None,
+            queue: VecDeque::new(),
+            plan: VecDeque::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn env<'a>(&self, state: &'a mut State) -> Environment<'a> {
+        Environment::new(state, &self.flags, 0, self.foresight)
+    }
+
+    /// Whether `piece` can be placed next, holding if needed.
+    #[must_use]
+    pub fn can_use(&self, piece: u8, env: &Environment) -> bool {
+        let mut upcoming = self.queue.iter().copied();
+        let Some(current) = upcoming.next() else {
+            return false;
+        };
+
+        piece == current
+            || (env.can_hold
+                && match self.hold {
+                    Some(h) => h == piece,
+                    None => upcoming.next() == Some(piece),
+                })
+    }
+
+    /// Locks `pair` and advances the queue, holding if it isn't the current piece.
+    ///
+    /// The plan is kept only if `pair` lands where its next placement would have.
+    pub fn play(&mut self, pair: Pair, env: &Environment) -> Result<(), ReplErr> {
+        let placement = pair
+            .locate(self.board, env)
+            .filter(|_| self.can_use(pair.0, env))
+            .ok_or_else(|| ReplErr::InvalidArgument("placement", pair.to_string()))?;
+
+        let planned = self
+            .plan
+            .pop_front()
+            .filter(|p| p.0 == pair.0)
+            .and_then(|p| p.locate(self.board, env));
+        if planned.map(|p| p.board) != Some(placement.board) {
+            self.plan.clear();
+        }
+
+        self.board = placement.board;
+        if let Some(current) = self.queue.pop_front()
+            && current != pair.0
+            // holding into an empty slot pulls the next piece as well
+            && self.hold.replace(current).is_none()
+        {
+            self.queue.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// The next placement of the plan, if it can still be made.
+    #[must_use]
+    pub fn suggestion(&self, env: &Environment) -> Option<Pair> {
+        self.plan
+            .front()
+            .copied()
+            .filter(|p| self.can_use(p.0, env) && p.locate(self.board, env).is_some())
+    }
+
+    /// Plans from what's known now: as many pcs as the queue allows from an empty board, otherwise the
+    /// quickest way to clear the board within `n` rows.
+    pub fn replan(&mut self, env: &Environment, pcs: &Map<Queue, History>) {
+        let queue: Queue = self.queue.iter().copied().take(Queue::CAPACITY).collect();
+        self.plan = if self.board.is_empty() {
+            max_pcs_in_queue(self.hold, queue, env, pcs)
+                .pcs
+                .into_iter()
+                .flat_map(|p| p.history.0)
+                .collect()
+        } else {
+            first_pc(self.board, self.hold, queue, self.n, env)
+                .map(|h| h.0.into_iter().collect())
+                .unwrap_or_default()
+        };
+    }
 }
 pub struct Repl<I, O> {
     pub i: I,
@@ -47,6 +174,8 @@ where
     pub fn spawn(mut self) -> ReplHandle {
         let running = Arc::new(AtomicBool::new(true));
         let run_clone = running.clone();
+        // killing the handle also stops whatever search is running
+        self.state.budget = self.state.budget.clone().watch(running.clone());
 
         let handle = thread::spawn(move || {
             let mut reader = BufReader::new(self.i);
@@ -73,59 +202,415 @@ where
         ReplHandle { running, handle }
     }
 
-    #[allow(clippy::missing_panics_doc)]
+    /// Answers a single line; failures are reported as `err <code> <message>` instead of panicking.
     #[must_use]
     pub fn respond(s: &mut State, arg: &str) -> String {
+        s.budget.start();
+        match panic::catch_unwind(AssertUnwindSafe(|| Self::dispatch(s, arg))) {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => e.to_string(),
+            Err(p) => ReplErr::Internal(
+                p.downcast_ref::<&str>()
+                    .map(ToString::to_string)
+                    .or_else(|| p.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "panicked".to_string()),
+            )
+            .to_string(),
+        }
+    }
+
+    fn dispatch(s: &mut State, arg: &str) -> Result<String, ReplErr> {
         let mut argv = arg.split_ascii_whitespace();
-        let Some(ma) = argv.next() else {
-            return "?".to_string();
-        };
+        let ma = next_arg(&mut argv, "command")?;
         match ma {
             "pcr" => {
-                let mut state = s.clone();
-                let e = Environment::new(&mut state, argv.next().unwrap(), 0, 0);
+                let plan = Self::plan(s, &mut argv)?;
+                Ok(plan
+                    .pcs
+                    .first()
+                    .map_or_else(|| "!".to_string(), |p| format_history(&p.history)))
+            }
+            "pca" => Ok(format_plan(&Self::plan(s, &mut argv)?)),
+            "pcp" => {
+                let flags = next_arg(&mut argv, "flags")?;
+                let vision = parse_arg(&mut argv, "vision")?;
+                let n = parse_arg(&mut argv, "n")?;
+                let force = argv.next().is_some_and(|x| x == "F");
 
-                let queue = argv.next().unwrap();
+                let e = Environment::new(s, flags, vision, 0);
+                e.pcs(n, force).map_err(ReplErr::Pcs)?;
+                Ok(String::new())
+            }
+            "pct" => {
+                let mut state = s.clone();
+                let e = Environment::new(&mut state, next_arg(&mut argv, "flags")?, 0, 0);
 
-                let n = argv.next().unwrap().parse().unwrap();
-                let pcs = if let Some(p) = s.pcs.get(&n) {
-                    p.clone()
-                } else {
-                    let z = e.pcs(n, false);
-                    s.pcs.insert(n, z.clone());
-                    z
-                };
+                let name = next_arg(&mut argv, "randomizer")?;
+                let randomizer = AnyRandomizer::load(name, &e.state.bag)
+                    .map_err(|_| ReplErr::InvalidArgument("randomizer", name.to_string()))?;
+                let hold = parse_hold(next_arg(&mut argv, "hold")?, &e)?;
+                let queue = parse_queue(next_arg(&mut argv, "queue")?, &e)?;
+                let n: usize = parse_arg(&mut argv, "n")?;
+                let len = parse_opt_arg(&mut argv, "len", n + 1)?;
 
-                let chosen =
-                    max_pcs_in_queue(queue.chars().map(|x| x as u8).collect::<Queue>(), &e, &pcs);
+                let pcs = table(s, &e, n)?;
 
-                if let Some(f) = chosen.1.first() {
-                    f.0.iter()
-                        .map(|x| format!("({}:{})", x.0 as char, x.1.fix_das()))
+                let p = pc_percent(hold, queue, &randomizer, len, &e, &pcs);
+                Ok(format!(
+                    "{:.2}% {}/{} {}",
+                    p.chance * 100.0,
+                    p.successes,
+                    p.total,
+                    p.fails
+                        .iter()
+                        .map(Queue::as_str)
                         .collect::<Vec<_>>()
                         .join(" ")
-                } else {
-                    "!".to_string()
-                }
+                )
+                .trim_end()
+                .to_string())
             }
-            "pcp" => {
-                let e = Environment::new(
-                    s,
-                    argv.next().unwrap(),
-                    argv.next().unwrap().parse().unwrap(),
-                    0,
-                );
-
-                let _ = e.pcs(
-                    argv.next().unwrap().parse().unwrap(),
-                    argv.next().is_some_and(|x| x == "F"),
-                );
-                String::new()
+            "pcb" => {
+                let mut state = s.clone();
+                let e = Environment::new(&mut state, next_arg(&mut argv, "flags")?, 0, 0);
+
+                let board = parse_board(next_arg(&mut argv, "board")?)?;
+                let hold = parse_hold(next_arg(&mut argv, "hold")?, &e)?;
+                let queue = parse_queue(next_arg(&mut argv, "queue")?, &e)?;
+                let height = parse_arg(&mut argv, "height")?;
+
+                let solutions = solve_pc(board, hold, queue, height, &e);
+                Ok(solutions.first().map_or_else(|| "!".to_string(), format_history))
+            }
+            "ren" => Self::ren(s, &mut argv),
+            "simulate" => Self::simulate(s, &mut argv),
+            "budget" => {
+                s.budget.time = match next_arg(&mut argv, "budget")? {
+                    "-" => None,
+                    ms => {
+                        Some(Duration::from_millis(ms.parse().map_err(|_| {
+                            ReplErr::InvalidArgument("budget", ms.to_string())
+                        })?))
+                    }
+                };
+                Ok(String::new())
             }
+            "start" | "push" | "play" | "next" | "stop" => Self::session(s, ma, &mut argv),
             // "ex" => std::process::abort(),
-            _ => "?".to_string(),
+            c => Err(ReplErr::UnknownCommand(c.to_string())),
+        }
+    }
+
+    /// `<flags> <hold|-> <queue> <n> [foresight]`, shared by `pcr` and `pca`.
+    fn plan<'a>(s: &mut State, argv: &mut impl Iterator<Item = &'a str>) -> Result<Plan, ReplErr> {
+        let mut state = s.clone();
+        let flags = next_arg(argv, "flags")?;
+        let hold = next_arg(argv, "hold")?;
+        let queue = next_arg(argv, "queue")?;
+        let n = parse_arg(argv, "n")?;
+        let foresight = parse_opt_arg(argv, "foresight", 0)?;
+
+        let e = Environment::new(&mut state, flags, 0, foresight);
+        let hold = parse_hold(hold, &e)?;
+        let queue = parse_queue(queue, &e)?;
+
+        let pcs = table(s, &e, n)?;
+
+        Ok(max_pcs_in_queue(hold, queue, &e, &pcs))
+    }
+
+    /// `start <flags> <n> [foresight]`, `push <pieces>`, `play <(piece:keys)>`, `next` and `stop`.
+    fn session<'a>(
+        s: &mut State,
+        command: &str,
+        argv: &mut impl Iterator<Item = &'a str>,
+    ) -> Result<String, ReplErr> {
+        match command {
+            "start" => {
+                let flags = next_arg(argv, "flags")?;
+                let n = parse_arg(argv, "n")?;
+                let foresight = parse_opt_arg(argv, "foresight", 0)?;
+
+                s.session = Some(Session::new(flags, n, foresight));
+                Ok(String::new())
+            }
+            "push" => {
+                let pieces = next_arg(argv, "pieces")?;
+                if pieces.bytes().any(|p| !s.bag.pieces().any(|x| x == p)) {
+                    return Err(ReplErr::InvalidArgument("pieces", pieces.to_string()));
+                }
+
+                let session = s.session.as_mut().ok_or(ReplErr::NoSession)?;
+                session.queue.extend(pieces.bytes());
+                Ok(String::new())
+            }
+            "play" => {
+                let pair: Pair = parse_arg(argv, "placement")?;
+                with_session(s, |s, session| session.play(pair, &session.env(s)))
+                    .map(|()| String::new())
+            }
+            "next" => with_session(s, Self::next),
+            "stop" => {
+                s.session = None;
+                Ok(String::new())
+            }
+            c => Err(ReplErr::UnknownCommand(c.to_string())),
         }
     }
+
+    /// The placement to make now, replanning only when play has left the last plan.
+    fn next(s: &mut State, session: &mut Session) -> Result<String, ReplErr> {
+        if session.suggestion(&session.env(s)).is_none() {
+            if session.board.is_empty() {
+                // taken out rather than cloned, since this runs every piece
+                let pcs = match s.pcs.remove(&session.n) {
+                    Some(p) => p,
+                    None => session.env(s).pcs(session.n, false).map_err(ReplErr::Pcs)?,
+                };
+                session.replan(&session.env(s), &pcs);
+                s.pcs.insert(session.n, pcs);
+            } else {
+                session.replan(&session.env(s), &Map::new());
+            }
+        }
+
+        Ok(session.suggestion(&session.env(s)).map_or_else(
+            || "!".to_string(),
+            |p| format_history(&History(smallvec![p])),
+        ))
+    }
+
+    /// `ren <flags> <board|-> <hold|-> <queue> [vision]`, placing every known piece unless `vision` is lower.
+    fn ren<'a>(s: &State, argv: &mut impl Iterator<Item = &'a str>) -> Result<String, ReplErr> {
+        let mut state = s.clone();
+        let flags = next_arg(argv, "flags")?;
+        let board = parse_board(next_arg(argv, "board")?)?;
+        let hold = next_arg(argv, "hold")?;
+        let queue = next_arg(argv, "queue")?;
+        let vision = parse_opt_arg(argv, "vision", usize::MAX)?;
+
+        let mut e = Environment::new(&mut state, flags, 0, 0);
+        let hold = parse_hold(hold, &e)?;
+        let queue: Vec<u8> = parse_queue(queue, &e)?.into_iter().collect();
+        e.vision = vision.min(queue.len() + usize::from(hold.is_some()));
+
+        Ok(match best_ren(board, hold, &queue, &e) {
+            Some(r) if !r.history.0.is_empty() => format!(
+                "{} | breaks={} | board={}",
+                format_history(&r.history),
+                r.breaks,
+                if r.board.is_empty() {
+                    "-".to_string()
+                } else {
+                    r.board.small()
+                }
+            ),
+            _ => "!".to_string(),
+        })
+    }
+
+    /// `simulate <flags> <pc|ren> <randomizer> <vision> <n> <pieces> [games] [seed]`
+    fn simulate<'a>(
+        s: &mut State,
+        argv: &mut impl Iterator<Item = &'a str>,
+    ) -> Result<String, ReplErr> {
+        let flags = next_arg(argv, "flags")?;
+        let planner: Planner = parse_arg(argv, "planner")?;
+        let name = next_arg(argv, "randomizer")?;
+        let vision = parse_arg(argv, "vision")?;
+        let n = parse_arg(argv, "n")?;
+        let pieces = parse_arg(argv, "pieces")?;
+        let games = parse_opt_arg(argv, "games", 1)?;
+        let seed = parse_opt_arg(argv, "seed", 0)?;
+
+        let randomizer = AnyRandomizer::load(name, &s.bag)
+            .map_err(|_| ReplErr::InvalidArgument("randomizer", name.to_string()))?;
+
+        let mut state = s.clone();
+        let e = Environment::new(&mut state, flags, vision, 0);
+
+        // combo doesn't need a table
+        let pcs = match planner {
+            Planner::Ren => Map::new(),
+            Planner::Pc => table(s, &e, n)?,
+        };
+
+        let sim = Simulation {
+            planner,
+            randomizer,
+            seed,
+            games,
+            pieces,
+            vision,
+        };
+
+        Ok(sim.run(&e, &pcs).to_string())
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplErr {
+    MissingArgument(&'static str),
+    InvalidArgument(&'static str, String),
+    UnknownCommand(String),
+    NoSession,
+    Pcs(PcsErr),
+    Internal(String),
+}
+
+impl ReplErr {
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingArgument(_) => "missing",
+            Self::InvalidArgument(..) => "invalid",
+            Self::UnknownCommand(_) => "unknown",
+            Self::NoSession => "session",
+            Self::Pcs(PcsErr::Io(_)) => "io",
+            Self::Pcs(PcsErr::Malformed(_) | PcsErr::Binary(_)) => "malformed",
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl Display for ReplErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "err {} ", self.code())?;
+        match self {
+            Self::MissingArgument(name) => write!(f, "expected `{name}`"),
+            Self::InvalidArgument(name, value) => write!(f, "`{value}` is not a valid {name}"),
+            Self::UnknownCommand(c) => write!(f, "`{c}` is not a command"),
+            Self::NoSession => write!(f, "no game is running; use `start`"),
+            Self::Pcs(e) => write!(f, "{e}"),
+            // keep the reply on one line
+            Self::Internal(m) => write!(f, "{}", m.replace('\n', " ")),
+        }
+    }
+}
+
+/// Runs `f` on the session taken out of `s`, putting it back afterwards even if `f` panics.
+fn with_session<T>(
+    s: &mut State,
+    f: impl FnOnce(&mut State, &mut Session) -> Result<T, ReplErr>,
+) -> Result<T, ReplErr> {
+    let mut session = s.session.take().ok_or(ReplErr::NoSession)?;
+    let r = panic::catch_unwind(AssertUnwindSafe(|| f(s, &mut session)));
+    s.session = Some(session);
+    r.unwrap_or_else(|p| panic::resume_unwind(p))
+}
+
+/// The pc table for `n`, cached in `s`.
+///
+/// A table cut short by the budget is cached as it is, so later lines use it instead of starting over.
+fn table(s: &mut State, e: &Environment, n: usize) -> Result<Map<Queue, History>, ReplErr> {
+    if let Some(p) = s.pcs.get(&n) {
+        return Ok(p.clone());
+    }
+
+    let pcs = e.pcs(n, false).map_err(ReplErr::Pcs)?;
+    s.pcs.insert(n, pcs.clone());
+    Ok(pcs)
+}
+
+/// `(piece:keys)` for every placement, with long key names and DAS expanded into taps.
+fn format_history(h: &History) -> String {
+    h.0.iter()
+        .map(|x| {
+            let spin = x.1.spin();
+            if spin.is_spin() {
+                format!("({}:{})[{spin}]", x.0 as char, x.1.fix_das())
+            } else {
+                format!("({}:{})", x.0 as char, x.1.fix_das())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `<count> | <pc> hold=<piece|-> | ... | rest=<queue|->`, or `!` without a pc.
+fn format_plan(plan: &Plan) -> String {
+    if plan.pcs.is_empty() {
+        return "!".to_string();
+    }
+
+    let piece = |p: Option<u8>| p.map_or_else(|| "-".to_string(), |x| (x as char).to_string());
+    let rest = if plan.rest.is_empty() {
+        "-".to_string()
+    } else {
+        plan.rest.as_str()
+    };
+
+    std::iter::once(plan.pcs.len().to_string())
+        .chain(
+            plan.pcs
+                .iter()
+                .map(|p| format!("{} hold={}", format_history(&p.history), piece(p.hold))),
+        )
+        .chain(std::iter::once(format!("rest={rest}")))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn next_arg<'a>(
+    argv: &mut impl Iterator<Item = &'a str>,
+    name: &'static str,
+) -> Result<&'a str, ReplErr> {
+    argv.next().ok_or(ReplErr::MissingArgument(name))
+}
+
+fn parse_arg<'a, T: FromStr>(
+    argv: &mut impl Iterator<Item = &'a str>,
+    name: &'static str,
+) -> Result<T, ReplErr> {
+    let v = next_arg(argv, name)?;
+    v.parse()
+        .map_err(|_| ReplErr::InvalidArgument(name, v.to_string()))
+}
+
+fn parse_opt_arg<'a, T: FromStr>(
+    argv: &mut impl Iterator<Item = &'a str>,
+    name: &'static str,
+    default: T,
+) -> Result<T, ReplErr> {
+    match argv.next() {
+        Some(v) => v
+            .parse()
+            .map_err(|_| ReplErr::InvalidArgument(name, v.to_string())),
+        None => Ok(default),
+    }
+}
+
+/// `-` for an empty hold, otherwise a single piece.
+fn parse_hold(v: &str, e: &Environment) -> Result<Option<u8>, ReplErr> {
+    match v {
+        "-" => Ok(None),
+        h => parse_queue(h, e)
+            .ok()
+            .filter(|q| q.len() == 1)
+            .and_then(|q| q.into_iter().next())
+            .map(Some)
+            .ok_or_else(|| ReplErr::InvalidArgument("hold", h.to_string())),
+    }
+}
+
+/// `-` for an empty board, otherwise rows from the top, separated by `|`.
+fn parse_board(v: &str) -> Result<Board, ReplErr> {
+    match v {
+        "-" => Ok(Board::empty()),
+        b => b
+            .parse()
+            .map_err(|_| ReplErr::InvalidArgument("board", b.to_string())),
+    }
+}
+
+/// Parses a queue, rejecting pieces the loaded bag doesn't define.
+fn parse_queue(v: &str, e: &Environment) -> Result<Queue, ReplErr> {
+    if v.len() > Queue::CAPACITY || v.bytes().any(|p| !e.state.bag.pieces().any(|x| x == p)) {
+        return Err(ReplErr::InvalidArgument("queue", v.to_string()));
+    }
+
+    let Ok(q) = v.parse();
+    Ok(q)
 }
 
 impl ReplHandle {
@@ -0,0 +1,272 @@
+//! Self-play: deals queues from a seed, lets a planner choose placements and keeps score.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::Board,
+    common::UnknownVariant,
+    environment::Environment,
+    input::Pair,
+    pc::{History, Map, max_pcs_in_queue},
+    piece::Queue,
+    randomizer::{Randomizer, Rng},
+    ren::best_ren,
+};
+
+/// Stack height that counts as topping out.
+pub const TOP: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Planner {
+    /// Plays out the first PC of the best plan [`max_pcs_in_queue`] finds, then plans again.
+    Pc,
+    /// Plays the first placement of the best path [`best_ren`] finds, then plans again.
+    Ren,
+}
+
+impl Display for Planner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Pc => "pc",
+                Self::Ren => "ren",
+            }
+        )
+    }
+}
+
+impl FromStr for Planner {
+    type Err = UnknownVariant;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pc" => Ok(Self::Pc),
+            "ren" | "combo" => Ok(Self::Ren),
+            c => Err(UnknownVariant(c.to_string())),
+        }
+    }
+}
+
+pub struct Simulation<R> {
+    pub planner: Planner,
+    pub randomizer: R,
+    /// Seeds for each game are drawn from this one.
+    pub seed: i32,
+    pub games: usize,
+    /// Most pieces placed in a single game.
+    pub pieces: usize,
+    /// Pieces the planner sees, not counting hold.
+    pub vision: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub games: usize,
+    pub pieces: usize,
+    pub pcs: usize,
+    /// Length of every combo, in line clears.
+    pub combos: Vec<usize>,
+    /// Games that ended because the planner found nothing, its plan couldn't be played, or the stack topped out.
+    pub failures: usize,
+    pub decisions: usize,
+    pub planning: Duration,
+}
+
+impl Report {
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn pcs_per_100(&self) -> f64 {
+        if self.pieces == 0 {
+            return 0.0;
+        }
+        self.pcs as f64 * 100.0 / self.pieces as f64
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn average_combo(&self) -> f64 {
+        if self.combos.is_empty() {
+            return 0.0;
+        }
+        self.combos.iter().sum::<usize>() as f64 / self.combos.len() as f64
+    }
+
+    #[must_use]
+    pub fn time_per_decision(&self) -> Duration {
+        self.planning
+            .checked_div(u32::try_from(self.decisions).unwrap_or(u32::MAX))
+            .unwrap_or_default()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "games={} pieces={} pcs={} pcs/100={:.2} combo={:.2} max_combo={} failures={} decisions={} ms/decision={:.3}",
+            self.games,
+            self.pieces,
+            self.pcs,
+            self.pcs_per_100(),
+            self.average_combo(),
+            self.combos.iter().max().copied().unwrap_or_default(),
+            self.failures,
+            self.decisions,
+            self.time_per_decision().as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// One game in progress.
+struct Game<I> {
+    board: Board,
+    hold: Option<u8>,
+    queue: VecDeque<u8>,
+    dealt: I,
+    combo: usize,
+}
+
+impl<I: Iterator<Item = u8>> Game<I> {
+    /// Keeps at least `n` pieces in the queue.
+    fn top_up(&mut self, n: usize) {
+        while self.queue.len() < n {
+            let Some(p) = self.dealt.next() else {
+                return;
+            };
+            self.queue.push_back(p);
+        }
+    }
+
+    /// Takes `piece` out of the current piece or hold, holding if needed and `can_hold` allows it.
+    fn take(&mut self, piece: u8, can_hold: bool) -> bool {
+        if self.queue.front() == Some(&piece) {
+            self.queue.pop_front();
+        } else if !can_hold {
+            return false;
+        } else if self.hold == Some(piece) {
+            self.hold = self.queue.pop_front();
+        } else if self.hold.is_none() && self.queue.get(1) == Some(&piece) {
+            self.hold = self.queue.pop_front();
+            self.queue.pop_front();
+        } else {
+            return false;
+        }
+
+        true
+    }
+}
+
+impl<R: Randomizer + Clone> Simulation<R> {
+    #[must_use]
+    pub fn run(&self, env: &Environment, pcs: &Map<Queue, History>) -> Report {
+        let mut report = Report::default();
+        let mut seeds = Rng::new(self.seed);
+
+        for _ in 0..self.games {
+            let mut game = Game {
+                board: Board::empty(),
+                hold: None,
+                queue: VecDeque::new(),
+                dealt: Rng::pieces(seeds.next(), self.randomizer.clone()),
+                combo: 0,
+            };
+
+            report.games += 1;
+            let placed = self.play(&mut game, env, pcs, &mut report);
+            report.pieces += placed;
+
+            if game.combo > 0 {
+                report.combos.push(game.combo);
+            }
+        }
+
+        report
+    }
+
+    /// Plays one game to the piece limit or the first failure, returning how many pieces were placed.
+    fn play<I: Iterator<Item = u8>>(
+        &self,
+        game: &mut Game<I>,
+        env: &Environment,
+        pcs: &Map<Queue, History>,
+        report: &mut Report,
+    ) -> usize {
+        let mut placed = 0;
+
+        while placed < self.pieces {
+            game.top_up(self.vision.max(2));
+
+            let started = Instant::now();
+            let plan = self.plan(game, env, pcs);
+            report.planning += started.elapsed();
+            report.decisions += 1;
+
+            if plan.is_empty() {
+                report.failures += 1;
+                return placed;
+            }
+
+            for pair in plan {
+                game.top_up(self.vision.max(2));
+                if !game.take(pair.0, env.can_hold) {
+                    report.failures += 1;
+                    return placed;
+                }
+
+                let Some(p) = pair.locate(game.board, env) else {
+                    report.failures += 1;
+                    return placed;
+                };
+
+                game.board = p.board;
+                placed += 1;
+
+                if p.lines > 0 {
+                    game.combo += 1;
+                } else if game.combo > 0 {
+                    report.combos.push(game.combo);
+                    game.combo = 0;
+                }
+
+                if p.lines > 0 && game.board.is_empty() {
+                    report.pcs += 1;
+                }
+
+                if game.board.height() > TOP {
+                    report.failures += 1;
+                    return placed;
+                }
+
+                if placed >= self.pieces {
+                    break;
+                }
+            }
+        }
+
+        placed
+    }
+
+    fn plan<I>(&self, game: &Game<I>, env: &Environment, pcs: &Map<Queue, History>) -> Vec<Pair> {
+        let window: Vec<u8> = game.queue.iter().take(self.vision).copied().collect();
+
+        match self.planner {
+            Planner::Pc => {
+                let plan = max_pcs_in_queue(game.hold, window.into_iter().collect(), env, pcs);
+                plan.pcs
+                    .first()
+                    .map(|p| p.history.0.to_vec())
+                    .unwrap_or_default()
+            }
+            Planner::Ren => best_ren(game.board, game.hold, &window, env)
+                .and_then(|r| r.history.0.first().copied())
+                .into_iter()
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    common::{UnknownVariant, rotation::Rotation},
+    input::{Input, Key},
+};
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Spin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+impl Spin {
+    #[must_use]
+    pub fn is_spin(self) -> bool {
+        self != Self::None
+    }
+}
+
+impl Display for Spin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::None => "none",
+                Self::Mini => "mini",
+                Self::Full => "spin",
+            }
+        )
+    }
+}
+
+impl FromStr for Spin {
+    type Err = UnknownVariant;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "mini" => Ok(Self::Mini),
+            "spin" | "full" => Ok(Self::Full),
+            c => Err(UnknownVariant(c.to_string())),
+        }
+    }
+}
+
+/// Which placements count as spins.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum SpinRule {
+    /// Guideline T-spins: 3 of the 4 diagonal corners filled, mini unless both front corners are.
+    TSpin,
+    /// TETR.IO's all-mini: T-spins as above, and any other piece that can't move after a rotation is a mini.
+    AllMini,
+    /// 3 filled corners out of the ones listed for the piece in the `.corners` file.
+    #[default]
+    Handheld,
+}
+
+impl Display for SpinRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::TSpin => "tspin",
+                Self::AllMini => "allmini",
+                Self::Handheld => "handheld",
+            }
+        )
+    }
+}
+
+impl FromStr for SpinRule {
+    type Err = UnknownVariant;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tspin" | "t" => Ok(Self::TSpin),
+            "allmini" | "all-mini" => Ok(Self::AllMini),
+            "handheld" => Ok(Self::Handheld),
+            c => Err(UnknownVariant(c.to_string())),
+        }
+    }
+}
+
+impl<const W: usize> Input<'_, W> {
+    /// Classifies the piece's current position under the environment's [`SpinRule`].
+    #[must_use]
+    pub fn spin(&self) -> Spin {
+        match self.last_successful_action {
+            Some(Key::RotateCW | Key::RotateCCW | Key::Rotate180) => {}
+            _ => return Spin::None,
+        }
+
+        match self.environment.state.spin_rule {
+            SpinRule::TSpin => self.t_spin(),
+            SpinRule::AllMini => {
+                if self.piece.name == b'T' {
+                    self.t_spin()
+                } else if self.is_immobile() {
+                    Spin::Mini
+                } else {
+                    Spin::None
+                }
+            }
+            SpinRule::Handheld => self.handheld_spin(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_spin(&self) -> bool {
+        self.spin().is_spin()
+    }
+
+    /// Whether the cell at an offset from the piece's center is a wall, the floor or a filled mino.
+    #[allow(
+        clippy::cast_possible_wrap,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn is_solid(&self, dx: i32, dy: i32) -> bool {
+        let x = self.piece.location.x as i32 + dx;
+        let y = self.piece.location.y as i32 + dy;
+
+        x < 0 || y < 0 || x >= W as i32 || self.board.get(x as usize, y as usize)
+    }
+
+    fn t_spin(&self) -> Spin {
+        if self.piece.name != b'T' {
+            return Spin::None;
+        }
+
+        let (front, back) = match self.piece.rotation {
+            Rotation::North => ([(-1, 1), (1, 1)], [(-1, -1), (1, -1)]),
+            Rotation::East => ([(1, 1), (1, -1)], [(-1, 1), (-1, -1)]),
+            Rotation::South => ([(-1, -1), (1, -1)], [(-1, 1), (1, 1)]),
+            Rotation::West => ([(-1, 1), (-1, -1)], [(1, 1), (1, -1)]),
+        };
+
+        let front = front.iter().filter(|&&(x, y)| self.is_solid(x, y)).count();
+        let back = back.iter().filter(|&&(x, y)| self.is_solid(x, y)).count();
+
+        if front + back < 3 {
+            return Spin::None;
+        }
+
+        // the "TST" kick always upgrades to a full spin
+        let tst = self
+            .last_kick
+            .is_some_and(|k| k.x.abs() == 1 && k.y.abs() == 2);
+
+        if front == 2 || tst {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    fn is_immobile(&self) -> bool {
+        [(-1, 0), (1, 0), (0, 1), (0, -1)].iter().all(|&(dx, dy)| {
+            let mut i = *self;
+            match (
+                i.piece.location.x.checked_add_signed(dx),
+                i.piece.location.y.checked_add_signed(dy),
+            ) {
+                (Some(x), Some(y)) => {
+                    i.piece.location.x = x;
+                    i.piece.location.y = y;
+                    !i.is_valid()
+                }
+                _ => true,
+            }
+        })
+    }
+
+    fn handheld_spin(&self) -> Spin {
+        let Some(corner_set) = self
+            .environment
+            .state
+            .corners
+            .get(self.piece.name, self.piece.rotation)
+        else {
+            return Spin::None;
+        };
+
+        let filled = corner_set
+            .corners
+            .iter()
+            .filter(|c| self.is_solid(c.x, c.y))
+            .count();
+
+        if filled >= 3 { Spin::Full } else { Spin::None }
+    }
+}
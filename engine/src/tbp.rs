@@ -0,0 +1,481 @@
+//! [Tetris Bot Protocol](https://github.com/tetris-bot-protocol/tbp-spec) front-end.
+//!
+//! TBP always describes a 10x40 matrix; the planners only see the 4-wide well at [`WELL_X`], so the
+//! columns either side of it have to be filled in for lines to clear.
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read, Write},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Board,
+    common::{coordinate::Coordinate, rotation::Rotation},
+    environment::Environment,
+    input::{Input, Pair, Placement},
+    pc::max_pcs_in_queue,
+    piece::Piece,
+    ren::best_ren,
+    repl::State,
+    spin::Spin,
+};
+
+/// Leftmost column of the 4-wide well inside the 10-wide matrix.
+pub const WELL_X: usize = 3;
+/// Size of the matrix TBP describes.
+pub const MATRIX_WIDTH: usize = 10;
+pub const MATRIX_HEIGHT: usize = 40;
+/// Pieces TBP games are played with.
+pub const PIECES: &[u8] = b"IJLOSTZ";
+/// Flags used for every search; TBP front-ends do their own pathfinding, so everything is allowed.
+pub const FLAGS: &str = "ftdhu";
+/// Size of the PC table the planner uses.
+pub const PC_SIZE: usize = 6;
+/// Pieces the combo search looks ahead.
+pub const REN_VISION: usize = 6;
+/// Time each suggestion may take; the searches answer with what they have once it's up.
+pub const THINK_TIME: Duration = Duration::from_millis(100);
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    Rules,
+    Start(Start),
+    NewPiece { piece: char },
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    Stop,
+    Quit,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Start {
+    pub hold: Option<char>,
+    pub queue: Vec<char>,
+    pub board: Vec<Vec<Option<char>>>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Info {
+        name: &'static str,
+        version: &'static str,
+        author: &'static str,
+        features: Vec<String>,
+    },
+    Ready,
+    Error {
+        reason: &'static str,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub location: Location,
+    pub spin: TbpSpin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub kind: char,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TbpSpin {
+    None,
+    Mini,
+    Full,
+}
+
+impl From<Rotation> for Orientation {
+    fn from(value: Rotation) -> Self {
+        match value {
+            Rotation::North => Self::North,
+            Rotation::East => Self::East,
+            Rotation::South => Self::South,
+            Rotation::West => Self::West,
+        }
+    }
+}
+
+impl From<Orientation> for Rotation {
+    fn from(value: Orientation) -> Self {
+        match value {
+            Orientation::North => Self::North,
+            Orientation::East => Self::East,
+            Orientation::South => Self::South,
+            Orientation::West => Self::West,
+        }
+    }
+}
+
+impl From<Spin> for TbpSpin {
+    fn from(value: Spin) -> Self {
+        match value {
+            Spin::None => Self::None,
+            Spin::Mini => Self::Mini,
+            Spin::Full => Self::Full,
+        }
+    }
+}
+
+impl Move {
+    /// Converts a placement made in the well into matrix coordinates.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    #[must_use]
+    pub fn from_placement(p: &Placement) -> Self {
+        Self {
+            location: Location {
+                kind: p.piece.name as char,
+                orientation: p.piece.rotation.into(),
+                x: (p.piece.location.x + WELL_X) as i32,
+                y: p.piece.location.y as i32,
+            },
+            spin: p.spin.into(),
+        }
+    }
+
+    /// The piece this move locks, in matrix coordinates.
+    #[must_use]
+    pub fn piece(&self) -> Option<Piece> {
+        Some(Piece {
+            name: u8::try_from(self.location.kind).ok()?,
+            rotation: self.location.orientation.into(),
+            location: Coordinate::new(
+                usize::try_from(self.location.x).ok()?,
+                usize::try_from(self.location.y).ok()?,
+            ),
+        })
+    }
+}
+
+/// What the bot knows about the game between `start` and `stop`.
+#[derive(Clone, Debug)]
+pub struct Game {
+    pub board: Board<10>,
+    pub hold: Option<u8>,
+    pub queue: VecDeque<u8>,
+    /// Remaining placements of the PC currently being built.
+    pub plan: VecDeque<Pair>,
+}
+
+impl Game {
+    /// Reads the starting position, failing with the reason to send back if the bot can't play it.
+    ///
+    /// The matrix has to be at most 10x40, with the 6 columns outside the well filled solid from the
+    /// bottom up to at least the top of the well's stack. Hold and queue may only hold [`PIECES`].
+    pub fn new(start: &Start) -> Result<Self, &'static str> {
+        if start.board.len() > MATRIX_HEIGHT
+            || start.board.iter().any(|row| row.len() > MATRIX_WIDTH)
+        {
+            return Err("invalid_board");
+        }
+        if !start.hold.iter().chain(&start.queue).all(|&c| is_piece(c)) {
+            return Err("invalid_piece");
+        }
+
+        let mut board = Board::empty();
+        for (y, row) in start.board.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                board.set(x, y, cell.is_some());
+            }
+        }
+
+        let game = Self {
+            board,
+            hold: start.hold.map(|x| x as u8),
+            queue: start.queue.iter().map(|&x| x as u8).collect(),
+            plan: VecDeque::new(),
+        };
+
+        let walls = game.walls();
+        if walls == 0 || game.well().height() > walls {
+            return Err("unsupported_board");
+        }
+
+        Ok(game)
+    }
+
+    /// Rows the columns outside the well fill solid from the bottom; 0 if anything else is out there.
+    #[must_use]
+    pub fn walls(&self) -> usize {
+        let outside: Vec<u64> = (0..MATRIX_WIDTH)
+            .filter(|x| !(WELL_X..WELL_X + 4).contains(x))
+            .map(|x| self.board.cols[x])
+            .collect();
+
+        // the unbroken run of full rows at the bottom
+        let full = outside.iter().fold(u64::MAX, |a, &c| a & c);
+        let solid = full & !full.wrapping_add(1);
+
+        if outside.iter().all(|&c| c == solid) {
+            solid.count_ones() as usize
+        } else {
+            0
+        }
+    }
+
+    /// The part of the matrix the planners work on.
+    #[must_use]
+    pub fn well(&self) -> Board {
+        let mut well = Board::empty();
+        well.cols.copy_from_slice(&self.board.cols[WELL_X..WELL_X + 4]);
+        well
+    }
+
+    /// Locks `mv` and advances the queue, holding if it used a different piece than the current one.
+    ///
+    /// Fails without changing anything if the piece is off the matrix or overlaps the stack.
+    pub fn play(&mut self, mv: &Move, env: &Environment) -> Result<(), &'static str> {
+        let Some(piece) = mv.piece().filter(|p| PIECES.contains(&p.name)) else {
+            return Err("invalid_move");
+        };
+
+        let mut i = Input::new(self.board, piece.name, env);
+        i.piece = piece;
+        if !i.is_valid() || piece.location.y >= MATRIX_HEIGHT {
+            return Err("invalid_move");
+        }
+        self.board = i.place(false).board;
+
+        let Some(current) = self.queue.pop_front() else {
+            return Ok(());
+        };
+        if current != piece.name {
+            // holding into an empty slot pulls the next piece as well
+            if self.hold.replace(current).is_none() {
+                self.queue.pop_front();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_piece(c: char) -> bool {
+    u8::try_from(c).is_ok_and(|c| PIECES.contains(&c))
+}
+
+pub struct Tbp<I, O> {
+    pub i: I,
+    pub o: O,
+    pub state: State,
+    pub game: Option<Game>,
+}
+
+impl<I, O> Tbp<I, O>
+where
+    I: Read,
+    O: Write,
+{
+    pub fn new(i: I, o: O, mut state: State) -> Self {
+        state.budget.time = Some(THINK_TIME);
+        Self {
+            i,
+            o,
+            state,
+            game: None,
+        }
+    }
+
+    /// Runs until `quit` or the end of input.
+    pub fn run(mut self) {
+        let mut reader = BufReader::new(self.i);
+        let mut line = String::new();
+
+        Self::send(
+            &mut self.o,
+            &BotMessage::Info {
+                name: "3res",
+                version: env!("CARGO_PKG_VERSION"),
+                author: "trueharuu",
+                features: vec![],
+            },
+        );
+
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            let Ok(message) = serde_json::from_str::<FrontendMessage>(line.trim_end()) else {
+                continue;
+            };
+
+            if matches!(message, FrontendMessage::Quit) {
+                break;
+            }
+
+            if let Some(response) = Self::respond(&mut self.state, &mut self.game, message) {
+                Self::send(&mut self.o, &response);
+            }
+        }
+    }
+
+    fn send(o: &mut O, message: &BotMessage) {
+        if let Ok(s) = serde_json::to_string(message) {
+            let _ = writeln!(o, "{s}");
+            let _ = o.flush();
+        }
+    }
+
+    #[must_use]
+    pub fn respond(
+        state: &mut State,
+        game: &mut Option<Game>,
+        message: FrontendMessage,
+    ) -> Option<BotMessage> {
+        match message {
+            FrontendMessage::Rules => {
+                // the planners need every tetromino in every rotation
+                let playable = PIECES.iter().all(|&p| {
+                    [
+                        Rotation::North,
+                        Rotation::East,
+                        Rotation::South,
+                        Rotation::West,
+                    ]
+                    .into_iter()
+                    .all(|r| state.bag.get(p, r).is_some())
+                });
+
+                Some(if playable {
+                    BotMessage::Ready
+                } else {
+                    BotMessage::Error {
+                        reason: "unsupported_rules",
+                    }
+                })
+            }
+            FrontendMessage::Start(start) => {
+                *game = None;
+                let g = match Game::new(&start) {
+                    Ok(g) => g,
+                    Err(reason) => return Some(BotMessage::Error { reason }),
+                };
+                *game = Some(g);
+
+                // loading the table can take a while, so it happens off the clock
+                state.budget.reset();
+                if !state.pcs.contains_key(&PC_SIZE) {
+                    let pcs = Environment::new(state, FLAGS, 0, 0).pcs(PC_SIZE, false);
+                    if let Ok(pcs) = pcs {
+                        state.pcs.insert(PC_SIZE, pcs);
+                    }
+                }
+                None
+            }
+            FrontendMessage::NewPiece { piece } => {
+                if !is_piece(piece) {
+                    return Some(BotMessage::Error {
+                        reason: "invalid_piece",
+                    });
+                }
+                if let Some(g) = game {
+                    g.queue.push_back(piece as u8);
+                }
+                None
+            }
+            FrontendMessage::Suggest => {
+                let g = game.as_mut()?;
+                state.budget.start();
+                Some(BotMessage::Suggestion {
+                    moves: suggest(state, g).into_iter().collect(),
+                })
+            }
+            FrontendMessage::Play { mv } => {
+                let g = game.as_mut()?;
+                let env = Environment::new(state, FLAGS, REN_VISION, 0);
+
+                let planned = g.plan.front().and_then(|p| p.locate(g.well(), &env));
+                if let Err(reason) = g.play(&mv, &env) {
+                    return Some(BotMessage::Error { reason });
+                }
+
+                if planned.map(|p| Move::from_placement(&p)) == Some(mv) {
+                    g.plan.pop_front();
+                } else {
+                    g.plan.clear();
+                }
+                None
+            }
+            FrontendMessage::Stop => {
+                *game = None;
+                None
+            }
+            FrontendMessage::Quit | FrontendMessage::Unknown => None,
+        }
+    }
+}
+
+/// Picks the next placement: continue the current PC, start a new one, or fall back to combo.
+fn suggest(state: &mut State, g: &mut Game) -> Option<Move> {
+    let well = g.well();
+
+    // the table is loaded on `start`; without one we can still play combo
+    if g.plan.is_empty()
+        && well.is_empty()
+        && let Some(pcs) = state.pcs.remove(&PC_SIZE)
+    {
+        let env = Environment::new(state, FLAGS, 0, 0);
+        let plan = max_pcs_in_queue(g.hold, g.queue.iter().copied().collect(), &env, &pcs);
+        if let Some(p) = plan.pcs.first() {
+            g.plan = p.history.0.iter().copied().collect();
+        }
+
+        state.pcs.insert(PC_SIZE, pcs);
+    }
+
+    let queue: Vec<u8> = g.queue.iter().copied().collect();
+    let vision = std::cmp::min(REN_VISION, queue.len() + usize::from(g.hold.is_some()));
+    let env = Environment::new(state, FLAGS, vision, 0);
+
+    if let Some(&p) = g.plan.front()
+        && let Some(placement) = p.locate(well, &env)
+    {
+        return Some(Move::from_placement(&placement));
+    }
+    g.plan.clear();
+
+    if let Some(ren) = best_ren(well, g.hold, &queue, &env)
+        && let Some(&first) = ren.history.0.first()
+        && let Some(placement) = first.locate(well, &env)
+    {
+        return Some(Move::from_placement(&placement));
+    }
+
+    // nothing keeps the combo going; take the lowest placement available
+    let current = *g.queue.front()?;
+    well.get_next_boards(current, &env)
+        .into_iter()
+        .min_by_key(|(p, _)| p.board.height())
+        .map(|(p, _)| Move::from_placement(&p))
+}
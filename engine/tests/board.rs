@@ -0,0 +1,121 @@
+mod common;
+
+use engine::{board::Board, environment::Environment, input::Placement};
+
+/// Every board `piece` can leave on `board`, in the compact form.
+fn drops<const W: usize>(board: &str, piece: u8) -> Vec<String> {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+    let board: Board<W> = board.parse().unwrap();
+    board
+        .get_next_boards(piece, &env)
+        .iter()
+        .map(|(p, _)| p.board.small())
+        .collect()
+}
+
+/// The placement of `piece` on `board` that leaves `after`.
+fn place(board: &str, piece: u8, after: &str) -> Placement {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+    let board: Board = board.parse().unwrap();
+    board
+        .get_next_boards(piece, &env)
+        .into_iter()
+        .map(|(p, _)| p)
+        .find(|p| p.board.small() == after)
+        .unwrap()
+}
+
+#[test]
+fn parses_rows_of_its_own_width() {
+    let b: Board<3> = "X__|XX_".parse().unwrap();
+    assert_eq!(
+        (b.small(), b.height(), b.num_minos()),
+        ("X__|XX_".into(), 2, 3)
+    );
+    assert!(b.get(0, 1) && !b.get(1, 1) && b.get(1, 0));
+
+    let b: Board<6> = "_____X|XXXXX_".parse().unwrap();
+    assert_eq!((b.height(), b.num_minos()), (2, 6));
+    assert!(b.get(5, 1) && !b.get(5, 0));
+
+    let b: Board<10> = "X_________||".parse().unwrap();
+    assert_eq!((b.small(), b.height()), ("X_________".into(), 1));
+
+    assert!("XXXX".parse::<Board<3>>().is_err());
+    assert!("XXXXX".parse::<Board<6>>().is_err());
+    assert!("XXXXXXXXX".parse::<Board<10>>().is_err());
+    assert!("XXXXXXXXX-".parse::<Board<10>>().is_err());
+}
+
+#[test]
+fn skim_drops_the_rows_above() {
+    let mut b: Board<3> = "X__|XXX|_X_|XXX".parse().unwrap();
+    b.skim();
+    assert_eq!((b.small(), b.height()), ("X__|_X_".into(), 2));
+
+    let mut b: Board<6> = "XXXXXX|X_____|XXXXXX".parse().unwrap();
+    b.skim();
+    assert_eq!(b.small(), "X_____");
+
+    let mut b: Board<10> = "__________|XXXXXXXXXX|X___X_____".parse().unwrap();
+    assert_eq!(b.height(), 2);
+    b.skim();
+    assert_eq!((b.small(), b.height()), ("X___X_____".into(), 1));
+
+    let mut b: Board<10> = "XXXXXXXXXX|XXXXXXXXXX".parse().unwrap();
+    b.skim();
+    assert!(b.is_empty());
+    assert_eq!(b.height(), 0);
+}
+
+#[test]
+fn i_lies_flat_only_where_it_fits() {
+    // three columns only take it upright
+    assert_eq!(
+        drops::<3>("", b'I'),
+        ["__X|__X|__X|__X", "_X_|_X_|_X_|_X_", "X__|X__|X__|X__"]
+    );
+
+    let six = drops::<6>("", b'I');
+    assert_eq!(six.len(), 3 + 6);
+    for flat in ["XXXX__", "_XXXX_", "__XXXX"] {
+        assert!(six.iter().any(|b| b == flat), "{flat}");
+    }
+
+    let ten = drops::<10>("", b'I');
+    assert_eq!(ten.len(), 7 + 10);
+    for x in 0..=6 {
+        let flat = format!("{}XXXX{}", "_".repeat(x), "_".repeat(6 - x));
+        assert!(ten.contains(&flat), "{flat}");
+    }
+}
+
+#[test]
+fn flat_i_clears_the_wide_rows() {
+    assert!(drops::<6>("XX____", b'I').iter().any(String::is_empty));
+    assert!(drops::<10>("XXXXXX____", b'I').iter().any(String::is_empty));
+    assert!(
+        drops::<10>("XXXXX_____", b'I')
+            .iter()
+            .all(|b| !b.is_empty())
+    );
+}
+
+#[test]
+fn placement_reports_the_rows_it_clears() {
+    let p = place("X___|XX__", b'O', "X_XX");
+    assert_eq!((p.lines, p.cleared, p.pc), (1, 0b1, false));
+
+    // the mask is of rows before the clear, so a gap between them shows
+    let p = place("XXX_|X___|XXX_", b'I', "___X|X__X");
+    assert_eq!((p.lines, p.cleared, p.pc), (2, 0b101, false));
+
+    let p = place("XX__|XX__", b'O', "");
+    assert_eq!((p.lines, p.cleared, p.pc), (2, 0b11, true));
+    assert!(p.board.is_empty());
+
+    let p = place("", b'T', "_X__|XXX_");
+    assert_eq!((p.lines, p.cleared, p.pc), (0, 0, false));
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use engine::{
+    board::Board,
+    budget::Budget,
+    environment::Environment,
+    pc::{generate_all_pc_queues, max_pcs_in_queue, solve_pc},
+    piece::Queue,
+    ren::best_ren,
+    repl::Repl,
+};
+
+#[test]
+fn cleared_flag_expires_the_budget() {
+    let running = Arc::new(AtomicBool::new(true));
+    let budget = Budget::default().watch(running.clone());
+    assert!(!budget.expired());
+
+    running.store(false, Ordering::SeqCst);
+    assert!(budget.expired());
+}
+
+#[test]
+fn deadline_only_counts_once_started() {
+    let mut budget = Budget::new(Some(Duration::ZERO));
+    assert!(!budget.expired());
+
+    budget.start();
+    assert!(budget.expired());
+
+    budget.reset();
+    assert!(!budget.expired());
+}
+
+#[test]
+fn searches_stop_when_out_of_time() {
+    let mut state = common::state();
+    state.budget = Budget::new(Some(Duration::ZERO));
+    state.budget.start();
+    let env = Environment::new(&mut state, "ftdhu", 6, 0);
+
+    let mut out = vec![];
+    generate_all_pc_queues::<4>(&mut out, 3, &env);
+    assert!(out.is_empty());
+
+    let queue: Queue = "IJLIS".parse().unwrap();
+    assert!(
+        max_pcs_in_queue(None, queue, &env, common::pcs())
+            .pcs
+            .is_empty()
+    );
+    assert!(solve_pc(Board::<4>::empty(), None, queue, 3, &env).is_empty());
+
+    assert!(best_ren(Board::<4>::empty(), None, b"IJLISZ", &env).is_none());
+}
+
+#[test]
+fn repl_restarts_the_clock_every_line() {
+    let mut state = common::state();
+    state.pcs.insert(3, common::pcs().clone());
+    let mut send = |line: &str| Repl::<&[u8], Vec<u8>>::respond(&mut state, line);
+
+    assert_eq!(send("pcr ftdhu - IJLIS 3"), "(I:)");
+    assert_eq!(send("budget 0"), "");
+    assert_eq!(send("pcr ftdhu - IJLIS 3"), "!");
+    assert_eq!(send("budget 60000"), "");
+    assert_eq!(send("pcr ftdhu - IJLIS 3"), "(I:)");
+    assert!(send("budget soon").starts_with("err invalid "));
+}
+
+#[test]
+fn tables_cut_short_stay_cached() {
+    let mut state = common::state();
+    let path = "data/srsx_ftdhu_handheld_2.pc";
+    assert!(!std::path::Path::new(path).exists());
+
+    let mut send = |line: &str| Repl::<&[u8], Vec<u8>>::respond(&mut state, line);
+    assert_eq!(send("budget 0"), "");
+    assert_eq!(send("pcr ftdhu - IJLIS 2"), "!");
+
+    // nothing was written, but the next line won't generate it again
+    assert!(!std::path::Path::new(path).exists());
+    assert!(state.pcs.contains_key(&2));
+}
@@ -0,0 +1,47 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use engine::{
+    budget::Budget,
+    environment::Environment,
+    file::{corners::Corners, kicks::Kicks, pc::PcTable, piece::Bag},
+    pc::{History, Map, generate_all_pc_queues},
+    piece::Queue,
+    randomizer::AnyRandomizer,
+    repl::State,
+    spin::SpinRule,
+};
+
+/// srsx kicks, tetrominoes and handheld corners, the way the bot runs.
+#[must_use]
+pub fn state() -> State {
+    let read = |path: &str| std::fs::read_to_string(path).unwrap();
+
+    State {
+        kicks: read("data/srsx.kick").parse::<Kicks>().unwrap(),
+        bag: read("data/tetromino.piece").parse::<Bag>().unwrap(),
+        corners: read("data/handheld.corners").parse::<Corners>().unwrap(),
+        spin_rule: SpinRule::Handheld,
+        randomizer: AnyRandomizer::default(),
+        fingerprint: ("srsx".into(), "tetromino".into(), "handheld".into()),
+        pcs: HashMap::new(),
+        session: None,
+        budget: Budget::default(),
+    }
+}
+
+/// The n=3 pc table for `ftdhu`, generated once per test binary without touching `data`.
+#[allow(dead_code)]
+#[must_use]
+pub fn pcs() -> &'static Map<Queue, History> {
+    static PCS: OnceLock<Map<Queue, History>> = OnceLock::new();
+
+    PCS.get_or_init(|| {
+        let mut state = state();
+        let env = Environment::new(&mut state, "ftdhu", 0, 0);
+        let mut out = vec![];
+        generate_all_pc_queues::<4>(&mut out, 3, &env);
+        PcTable::from_text(&String::from_utf8(out).unwrap())
+            .unwrap()
+            .into_map()
+    })
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use engine::{board::Board, common::rotation::Rotation, file::piece::Mask};
+
+fn mask(piece: u8, rotation: Rotation) -> Mask {
+    let bag = common::state().bag;
+    bag.get(piece, rotation).unwrap().mask.clone()
+}
+
+#[test]
+fn columns_start_at_the_lowest_leftmost_cell() {
+    let t = mask(b'T', Rotation::North);
+    assert_eq!((t.left, t.bottom), (-1, 0));
+    assert_eq!(t.cols.as_slice(), [0b01, 0b11, 0b01]);
+
+    let i = mask(b'I', Rotation::East);
+    assert_eq!((i.left, i.bottom), (0, -2));
+    assert_eq!(i.cols.as_slice(), [0b1111]);
+}
+
+#[test]
+fn fits_inside_a_narrow_board() {
+    let t = mask(b'T', Rotation::North);
+    let empty = Board::<3>::empty();
+    assert!(t.fits(&empty, 1, 0));
+    // walls either side
+    assert!(!t.fits(&empty, 0, 0));
+    assert!(!t.fits(&empty, 2, 0));
+
+    // the floor; the south T hangs a cell below its center
+    let s = mask(b'T', Rotation::South);
+    assert!(!s.fits(&empty, 1, 0));
+    assert!(s.fits(&empty, 1, 1));
+
+    let board: Board<3> = "_X_|X_X".parse().unwrap();
+    assert!(!t.fits(&board, 1, 0));
+    assert!(!t.fits(&board, 1, 1));
+    assert!(t.fits(&board, 1, 2));
+    assert!(!s.fits(&board, 1, 1));
+    assert!(!mask(b'I', Rotation::East).fits(&board, 1, 2));
+}
+
+#[test]
+fn fits_across_a_wide_board() {
+    let i = mask(b'I', Rotation::North);
+    let empty = Board::<10>::empty();
+    for x in 1..=7 {
+        assert!(i.fits(&empty, x, 0), "{x}");
+    }
+    assert!(!i.fits(&empty, 0, 0));
+    assert!(!i.fits(&empty, 8, 0));
+
+    // a single cell high up blocks only the row it's on
+    let mut board = Board::<10>::empty();
+    board.set(9, 40, true);
+    assert!(!i.fits(&board, 7, 40));
+    assert!(i.fits(&board, 7, 39));
+    assert!(i.fits(&board, 7, 41));
+    assert!(i.fits(&board, 6, 40));
+}
+
+#[test]
+fn lock_fills_the_cells_on_the_board() {
+    let mut board = Board::<3>::empty();
+    mask(b'T', Rotation::North).lock(&mut board, 1, 0);
+    assert_eq!(board.small(), "_X_|XXX");
+
+    let mut board: Board<10> = "XXXXXX____".parse().unwrap();
+    mask(b'I', Rotation::North).lock(&mut board, 7, 0);
+    assert_eq!(board.full_rows(), 1);
+
+    // cells past the right wall are dropped
+    let mut board = Board::<10>::empty();
+    mask(b'I', Rotation::North).lock(&mut board, 8, 3);
+    assert_eq!(board.small(), "_______XXX|__________|__________|__________");
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use engine::{board::Board, environment::Environment, input::Finesse, spin::Spin};
+
+fn keys(f: Finesse) -> String {
+    f.into_iter()
+        .map(|k| k.short())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[test]
+fn placements_come_in_finesse_order() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+    let keyboard = env.keyboard();
+    let rank = |f: Finesse| {
+        let order: Vec<usize> = f
+            .into_iter()
+            .map(|k| keyboard.iter().position(|&x| x == k).unwrap())
+            .collect();
+        (f.len, order)
+    };
+
+    for board in ["", "X___|XX__", "X__X|X__X|XX_X", "XX__|XX__"] {
+        let board: Board = board.parse().unwrap();
+        for piece in *b"IJLOSTZ" {
+            let next = board.get_next_boards(piece, &env);
+            assert!(
+                next.windows(2).all(|w| rank(w[0].1) < rank(w[1].1)),
+                "{} on {}",
+                piece as char,
+                board.small()
+            );
+        }
+    }
+}
+
+#[test]
+fn placements_are_reproducible() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+
+    let next: Vec<String> = Board::<4>::empty()
+        .get_next_boards(b'T', &env)
+        .into_iter()
+        .map(|(p, f)| format!("{} {}", p.board.small(), keys(f)))
+        .collect();
+
+    assert_eq!(
+        next,
+        [
+            "_X__|XXX_ ",
+            "__X_|_XXX r",
+            "_X__|_XX_|_X__ cw",
+            "_X__|XX__|_X__ ccw",
+            "XXX_|_X__ f",
+            "__X_|__XX|__X_ r,cw",
+            "__X_|_XX_|__X_ r,ccw",
+            "_XXX|__X_ r,f",
+            "X___|XX__|X___ cw,l",
+            "___X|__XX|___X ccw,dr",
+        ]
+    );
+}
+
+#[test]
+fn find_matches_the_placement_search() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+
+    for board in ["", "X___|___X|X_XX", "__XX|___X|X_XX", "XX__|X___|X_XX"] {
+        let board: Board = board.parse().unwrap();
+        for piece in *b"IJLOSTZ" {
+            for (placement, finesse) in board.get_next_boards(piece, &env) {
+                let found = placement.piece.find(board, &env).unwrap();
+                assert_eq!(keys(found), keys(finesse));
+                assert_eq!(found.spin(), placement.spin);
+            }
+        }
+    }
+
+    // t-spin double
+    let board: Board = "X___|___X|X_XX".parse().unwrap();
+    let (placement, _) = board
+        .get_next_boards(b'T', &env)
+        .into_iter()
+        .find(|(p, _)| p.lines == 2)
+        .unwrap();
+    let found = placement.piece.find(board, &env).unwrap();
+    assert_eq!(keys(found), "cw,sd,cw");
+    assert_eq!(found.spin(), Spin::Full);
+}
+
+#[test]
+fn find_rejects_unreachable_placements() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+
+    let (placement, _) = Board::<4>::empty().get_next_boards(b'O', &env)[0];
+    assert!(placement.piece.find(Board::<4>::empty(), &env).is_some());
+
+    // too narrow a gap to get under
+    let roofed: Board = "XX_X|____|____".parse().unwrap();
+    assert!(placement.piece.find(roofed, &env).is_none());
+}
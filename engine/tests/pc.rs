@@ -0,0 +1,199 @@
+mod common;
+
+use std::collections::{HashSet, VecDeque};
+
+use engine::{
+    board::Board,
+    environment::Environment,
+    file::{kicks::Kicks, piece::Bag},
+    input::Pair,
+    pc::{History, Percent, Plan, generate_all_pc_queues, max_pcs_in_queue, pc_percent, solve_pc},
+    piece::Queue,
+    randomizer::Builtin,
+};
+use smallvec::smallvec;
+
+fn plan(flags: &str, hold: Option<u8>, queue: &str) -> Plan {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, flags, 0, 0);
+
+    let queue: Queue = queue.bytes().collect();
+    max_pcs_in_queue(hold, queue, &env, common::pcs())
+}
+
+fn queues(plan: &Plan) -> Vec<String> {
+    plan.pcs.iter().map(|p| p.history.queue_str()).collect()
+}
+
+#[test]
+fn hold_reorders_the_queue() {
+    assert_eq!(queues(&plan("ftdhu", None, "OI")), ["I"]);
+    assert_eq!(queues(&plan("ftdhu", Some(b'I'), "O")), ["I"]);
+    // the last pc is the held I by itself
+    assert_eq!(
+        queues(&plan("ftdhu", Some(b'O'), "IJLI")),
+        ["I", "JLO", "I"]
+    );
+}
+
+#[test]
+fn holdless_takes_the_queue_as_given() {
+    assert!(plan("ftdu", None, "OI").pcs.is_empty());
+    assert!(plan("ftdu", Some(b'I'), "O").pcs.is_empty());
+    assert_eq!(queues(&plan("ftdu", Some(b'O'), "IJLI")), ["I"]);
+    assert_eq!(queues(&plan("ftdu", None, "IIII")), ["I", "I", "I", "I"]);
+}
+
+#[test]
+fn plan_tracks_hold_and_rest() {
+    let holds = |p: &Plan| -> Vec<_> { p.pcs.iter().map(|x| x.hold).collect() };
+
+    let p = plan("ftdhu", Some(b'O'), "IJLIS");
+    assert_eq!(queues(&p), ["I", "JLO", "I"]);
+    assert_eq!(holds(&p), [Some(b'O'), None, None]);
+    assert_eq!(p.hold, None);
+    assert_eq!(p.rest.as_str(), "S");
+
+    let p = plan("ftdu", Some(b'O'), "IJLIS");
+    assert_eq!(holds(&p), [Some(b'O')]);
+    assert_eq!(p.hold, Some(b'O'));
+    assert_eq!(p.rest.as_str(), "JLIS");
+
+    let p = plan("ftdhu", None, "SZ");
+    assert_eq!(p.hold, None);
+    assert_eq!(p.rest.as_str(), "SZ");
+}
+
+#[test]
+fn percent_over_the_rest_of_the_bag() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+    let fails = |p: &Percent| -> Vec<_> { p.fails.iter().map(|q| q.as_str()).collect() };
+
+    // any of IOSTZ is next, and only I doesn't fit with JL
+    let p = pc_percent(
+        None,
+        "JL".parse().unwrap(),
+        &Builtin::Bag7,
+        3,
+        &env,
+        common::pcs(),
+    );
+    assert_eq!((p.successes, p.total), (4, 5));
+    assert!((p.chance - 0.8).abs() < 1e-9);
+    assert_eq!(fails(&p), ["I"]);
+
+    let p = pc_percent(None, Queue::new(), &Builtin::Bag7, 1, &env, common::pcs());
+    assert_eq!((p.successes, p.total), (1, 7));
+    assert!((p.chance - 1.0 / 7.0).abs() < 1e-9);
+    assert_eq!(fails(&p), ["J", "L", "O", "S", "T", "Z"]);
+}
+
+#[test]
+fn foresight_breaks_ties_by_leftover() {
+    let p = plan("ftdhu", None, "IJJLO");
+    assert_eq!(queues(&p), ["I", "JJ"]);
+    assert_eq!((p.hold, p.rest.as_str()), (None, "LO".into()));
+
+    // both make 2 pcs, but a held J is likelier to start a third than LO
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 3);
+    let p = max_pcs_in_queue(None, "IJJLO".parse().unwrap(), &env, common::pcs());
+    assert_eq!(queues(&p), ["I", "JLO"]);
+    assert_eq!((p.hold, p.rest.as_str()), (Some(b'J'), String::new()));
+}
+
+#[test]
+fn solve_counts_the_minos_of_each_piece() {
+    let mut state = common::state();
+    // an upside-down U pentomino, the same in every rotation
+    let u: Bag = ["N", "E", "S", "W"]
+        .map(|r| format!("U.{r}=(-1,+0)(+1,+0)(-1,+1)(+0,+1)(+1,+1)@Z"))
+        .join("\n")
+        .parse()
+        .unwrap();
+    let kicks: Kicks = [
+        "NE", "ES", "SW", "WN", "NW", "WS", "SE", "EN", "NS", "EW", "SN", "WE",
+    ]
+    .map(|r| format!("U.{r}=(0,0)"))
+    .join("\n")
+    .parse()
+    .unwrap();
+    state.bag.shapes.extend(u.shapes);
+    state.kicks.entries.extend(kicks.entries);
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+
+    // 5 cells to fill, more than any tetromino has
+    let board: Board = "___X|_X_X".parse().unwrap();
+    let solutions = solve_pc(board, None, "U".parse().unwrap(), 2, &env);
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0].0[0].to_string(), "(U:)");
+
+    assert!(solve_pc(board, None, "T".parse().unwrap(), 2, &env).is_empty());
+}
+
+/// The pc table the way the generator first worked: one state at a time off a FIFO queue, with no
+/// caching, so it can check the parallel one.
+fn sequential_table(n: usize, env: &Environment) -> Vec<String> {
+    let mut queue = VecDeque::from([(Board::<4>::empty(), History(smallvec![]))]);
+    let mut visited = HashSet::new();
+    let mut pcs = HashSet::new();
+    let mut lines = vec![];
+
+    while let Some((board, history)) = queue.pop_front() {
+        if !visited.insert((board, history.queue())) || pcs.contains(&history.queue()) {
+            continue;
+        }
+
+        for piece in env.state.bag.pieces() {
+            for (placement, f) in board.get_next_boards(piece, env) {
+                if placement.board.height() > n {
+                    continue;
+                }
+
+                let mut next = history.clone();
+                next.0.push(Pair(piece, f));
+                if placement.pc {
+                    if pcs.insert(next.queue()) {
+                        let pairs: Vec<String> = next.0.iter().map(ToString::to_string).collect();
+                        lines.push(format!("{} = {}", next.queue_str(), pairs.join(" ")));
+                    }
+                } else if next.0.len() < n {
+                    queue.push_back((placement.board, next));
+                }
+            }
+        }
+    }
+
+    lines.sort_unstable();
+    lines
+}
+
+#[test]
+fn generates_the_known_table() {
+    let fixture = include_str!("fixtures/srsx_ftdhu_handheld_3.pc");
+    let fixture: Vec<&str> = fixture.lines().filter(|l| !l.starts_with('#')).collect();
+
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+    let mut out = vec![];
+    generate_all_pc_queues::<4>(&mut out, 3, &env);
+    let out = String::from_utf8(out).unwrap();
+    let mut lines: Vec<&str> = out.lines().collect();
+    lines.sort_unstable();
+    assert_eq!(lines, fixture);
+    assert_eq!(sequential_table(3, &env), fixture);
+
+    // and every line in it really is a pc
+    for line in fixture {
+        let (_, pairs) = line.split_once(" = ").unwrap();
+        let mut board = Board::<4>::empty();
+        let mut pc = false;
+        for pair in pairs.split(' ') {
+            let placement = pair.parse::<Pair>().unwrap().locate(board, &env).unwrap();
+            board = placement.board;
+            pc = placement.pc;
+        }
+        assert!(pc, "{line}");
+    }
+}
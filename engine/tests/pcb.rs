@@ -0,0 +1,53 @@
+mod common;
+
+use engine::{
+    environment::Environment,
+    file::pc::{PcTable, PcbParseErr},
+};
+
+const TABLE: &str = include_str!("fixtures/srsx_ftdhu_handheld_3.pc");
+
+#[test]
+fn binary_round_trips() {
+    let table = PcTable::from_text(TABLE).unwrap();
+    assert_eq!(table.to_text(), TABLE);
+
+    let decoded = PcTable::decode(&table.encode()).unwrap();
+    assert_eq!(decoded, table);
+    assert_eq!(decoded.to_text(), TABLE);
+    assert_eq!(decoded.into_map(), table.into_map());
+}
+
+#[test]
+fn keeps_spins_and_trailing_newline() {
+    let text = "#n=2\nI = (I:)\nT = (T:cw,sd,cw)[spin]\n";
+    let table = PcTable::from_text(text).unwrap();
+    assert_eq!(PcTable::decode(&table.encode()).unwrap().to_text(), text);
+}
+
+#[test]
+fn rejects_other_files() {
+    assert!(matches!(
+        PcTable::decode(b"I = (I:)"),
+        Err(PcbParseErr::BadMagic)
+    ));
+}
+
+#[test]
+fn broken_cache_falls_back_to_text() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "f", 0, 0);
+    let path = "data/srsx_f----_handheld_1.pc";
+    let bin_path = format!("{path}b");
+
+    std::fs::write(path, "#n=1\nI = (I:)\n").unwrap();
+    std::fs::write(&bin_path, b"3RPC\xffgarbage").unwrap();
+    let pcs = env.pcs(1, false);
+    let rewritten = std::fs::read(&bin_path).unwrap();
+
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(&bin_path).unwrap();
+
+    assert_eq!(pcs.unwrap().len(), 1);
+    assert!(PcTable::decode(&rewritten).is_ok());
+}
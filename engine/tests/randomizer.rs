@@ -0,0 +1,332 @@
+use engine::{
+    file::randomizer::Custom,
+    randomizer::{Builtin, Randomizer, Rng},
+};
+
+// (randomizer, seed, first 50 pieces), dealt by TETR.IO's JS generator (`RNG` and the bag functions)
+const VECTORS: &[(&str, i32, &str)] = &[
+    (
+        "7-bag",
+        1,
+        "OJILSTZTOLJSIZLISOTZJJSLITZOITLOZSJJSOZLITOSZLJTIT",
+    ),
+    (
+        "7-bag",
+        42,
+        "OTILJSZIJZLOSTIJSZLOTLTJSOIZOJTLIZSITOSJZLOZJLTIST",
+    ),
+    (
+        "7-bag",
+        123_456_789,
+        "SZTOIJLTSIOJZLLJTZSIOLOJSITZSIOTZJLZJOLTSIOSJZTILT",
+    ),
+    (
+        "7-bag",
+        2_147_483_646,
+        "ZSIOLJTOSIZJLTOSZTIJLTZSLOJIJZSTIOLOJTISLZJISTZLOO",
+    ),
+    (
+        "14-bag",
+        1,
+        "ZOSLITITJSJOLZSLJLIIOOSJZTTZJJSSTIZOTOZILLJOOTLLIZ",
+    ),
+    (
+        "14-bag",
+        42,
+        "JISTIJOZLSOLTZSILLZJOTZJIOSTZLSOSITLZOIJTJOJJLOZLZ",
+    ),
+    (
+        "14-bag",
+        123_456_789,
+        "ZILJTSLOZITOJSOLJIZJTLISOZTSTZJZSLSIJTOOILTJSOILZJ",
+    ),
+    (
+        "14-bag",
+        2_147_483_646,
+        "SOSTZLJLZIJOITLTSZOLIZIOSJJTTLOISLTJOZSZIJILJTTIJZ",
+    ),
+    (
+        "classic",
+        1,
+        "ZLTSILZJTSITZSJZSZSJIJIZJSJJOZJOJTOLJTJZJLSTSLOLST",
+    ),
+    (
+        "classic",
+        42,
+        "ZIJOSLSIOZTSLLOZTITJZILTSSLZTJITIOSOIOZILSOLZSZSJL",
+    ),
+    (
+        "classic",
+        123_456_789,
+        "LJISZOZIZSJTZOZTZLZOTOJITSILTSLIOIOLJZOTZJZTJOJTSS",
+    ),
+    (
+        "classic",
+        2_147_483_646,
+        "TLISTOZISLTSOIIOSZLSOIOZLJLJOLZJTZOLOOZJILITJJIZTZ",
+    ),
+    (
+        "pairs",
+        1,
+        "OJJOJOOTOOTTSSLLLSTJJJTTLOOLLOTJTJJTOSSOSOLLOLOOOZ",
+    ),
+    (
+        "pairs",
+        42,
+        "OTOTOTILIILLLSSLSLZLLLZZISSSIIIIJJJIOTOTTOIOIOOIJZ",
+    ),
+    (
+        "pairs",
+        123_456_789,
+        "SZZZSSLJLLJJIOOIIOTLTTLLSJJSSJZTTZZTOTOOTTIIIZZZTZ",
+    ),
+    (
+        "pairs",
+        2_147_483_646,
+        "ZSZSZSOOOZZZSIISSIIIOIOOSIIISSTLLTTLIOOIIOISIISSJI",
+    ),
+    (
+        "total-mayhem",
+        1,
+        "ZZJSSLZIITOSJZZSIZOZOIITJSZIOITJLZJOIJTOLTJJIZITLS",
+    ),
+    (
+        "total-mayhem",
+        42,
+        "ZSJLOLTSSLZJTSLLLOZJSJJZSZTSSSLZZTTJSJIOOLOIOZSLSO",
+    ),
+    (
+        "total-mayhem",
+        123_456_789,
+        "LTJSOZLZZIZSOJJZTOZZTJZZLTZOJLISTSTILJOLIOIOLJZOJJ",
+    ),
+    (
+        "total-mayhem",
+        2_147_483_646,
+        "TTLSSJTOOZISLTTSOTITIOOZLSTOIOZLJTLIOLZIJZLLOTOZJS",
+    ),
+    (
+        "7+1-bag",
+        1,
+        "IZSZTOJLTLJZISOIJSLITIOZTLJJOSZITLOISLJZTOLZSIJLTZ",
+    ),
+    (
+        "7+1-bag",
+        42,
+        "SZOZTLJISSTLJIZOLTJSLIZOSIZSJOTLJZLSOITZTJIZLSOOST",
+    ),
+    (
+        "7+1-bag",
+        123_456_789,
+        "LITZOSJLLTISZOZJLTJSITZOTSJLIOZLOILJZTTSTSZJLIIOLJ",
+    ),
+    (
+        "7+1-bag",
+        2_147_483_646,
+        "TZIJOSLTLTJSOOIZTZSLOJIOLIZLJOTSOJSZTILJOJISJTLZZJ",
+    ),
+    (
+        "7+2-bag",
+        1,
+        "IJZOZLZSTOILTJSSZOITLOZSIOJTTJISLJZOLOJLSZITTLJIST",
+    ),
+    (
+        "7+2-bag",
+        42,
+        "SZJSILZOTOZIJZTLSJOJJLISSZTJZLSOITLZILLOTZOJSSLTLO",
+    ),
+    (
+        "7+2-bag",
+        123_456_789,
+        "TTSJLZOILSOZLIZJTSSIOTTJJZLOILJZSTSIOSITZILOJZIOSI",
+    ),
+    (
+        "7+2-bag",
+        2_147_483_646,
+        "TTZLJTSIOZJISSOTILJIOTIOLZSOJSZLILZTSITZJJZLOSLSZO",
+    ),
+    (
+        "7+x-bag",
+        1,
+        "LIISOOJJTZSSLLTOZJIITLOZSJTSTIOZLJZSIOZLTJLITOZSJL",
+    ),
+    (
+        "7+x-bag",
+        42,
+        "OJTSOZLTIIJLSTIZJLOOJTLISSZJZOSITZLTOZILSJLSTJZOIT",
+    ),
+    (
+        "7+x-bag",
+        123_456_789,
+        "JSTLTSIZZOLITSJIOOZSIOTJJLZIZLTOLSJISLTZOJTJIZSLOI",
+    ),
+    (
+        "7+x-bag",
+        2_147_483_646,
+        "JLITZSZSOILJZOLSTOIJJSTIOLZSTOTJILZTOJISZLJSZTIOLZ",
+    ),
+];
+
+fn deal(randomizer: &str, seed: i32, len: usize) -> String {
+    let randomizer: Builtin = randomizer.parse().unwrap();
+    Rng::pieces(seed, randomizer)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[test]
+fn known_sequences() {
+    for &(randomizer, seed, expected) in VECTORS {
+        assert_eq!(
+            deal(randomizer, seed, expected.len()),
+            expected,
+            "{randomizer} with seed {seed}"
+        );
+    }
+}
+
+#[test]
+fn seeds_wrap_like_tetrio() {
+    // 0 and 2^31 - 1 both become 2^31 - 2
+    for seed in [0, i32::MAX] {
+        assert_eq!(deal("7-bag", seed, 21), deal("7-bag", Rng::MAX_SEED, 21));
+    }
+}
+
+#[test]
+fn bags_hold_every_piece() {
+    for &(randomizer, seed, _) in VECTORS {
+        let size = match randomizer {
+            "7-bag" => 7,
+            "14-bag" => 14,
+            _ => continue,
+        };
+
+        let queue = deal(randomizer, seed, size * 4);
+        for bag in queue.as_bytes().chunks(size) {
+            let mut bag = bag.to_vec();
+            bag.sort_unstable();
+            let mut full = Rng::BAG.repeat(size / 7);
+            full.sort_unstable();
+            assert_eq!(bag, full, "{randomizer} with seed {seed}");
+        }
+    }
+}
+
+#[test]
+fn deals_matches_pieces() {
+    let queue = deal("7+x-bag", 42, 30);
+    assert!(Rng::deals(42, &Builtin::Bag7PX, queue.as_bytes()));
+    assert!(!Rng::deals(43, &Builtin::Bag7PX, queue.as_bytes()));
+}
+
+#[test]
+fn recover_finds_the_seed() {
+    let queue = deal("7-bag", 42, 21);
+    assert_eq!(
+        Rng::recover(queue.as_bytes(), &Builtin::Bag7, 1..=100_000, 16),
+        [42]
+    );
+
+    // a single piece can't tell seeds apart, so the limit cuts the search short
+    let first = &queue.as_bytes()[..1];
+    let seeds = Rng::recover(first, &Builtin::Bag7, 1..=100_000, 4);
+    assert_eq!(seeds.len(), 4);
+    assert!(seeds.is_sorted());
+    assert!(seeds.iter().all(|&s| Rng::deals(s, &Builtin::Bag7, first)));
+}
+
+#[test]
+fn recover_finds_nothing_for_an_impossible_queue() {
+    // no 7-bag deals the same piece three times in a row
+    assert!(Rng::recover(b"III", &Builtin::Bag7, 1..=100_000, 16).is_empty());
+}
+
+#[test]
+fn custom_matches_builtin() {
+    let cases = [
+        (Builtin::Bag7, "pieces=ZLOSIJT"),
+        (Builtin::Bag14, "pieces=ZLOSIJT\ncopies=2"),
+        (Builtin::Bag7P1, "pieces=ZLOSIJT\nextras=1"),
+        (Builtin::Bag7P2, "pieces=ZLOSIJT\nextras=2"),
+        (
+            Builtin::Bag7PX,
+            "pieces=ZLOSIJT\nextras=3,2,1,1,0\nextras_from=pool",
+        ),
+    ];
+
+    for (builtin, file) in cases {
+        let custom: Custom = file.parse().unwrap();
+        for &seed in &[1, 42, Rng::MAX_SEED] {
+            let a: Vec<u8> = Rng::pieces(seed, builtin).take(50).collect();
+            let b: Vec<u8> = Rng::pieces(seed, custom.clone()).take(50).collect();
+            assert_eq!(a, b, "{builtin} with seed {seed}");
+        }
+
+        let a = builtin.continuations(b"ZLO", 5);
+        let b = custom.continuations(b"ZLO", 5);
+        assert_eq!(a.len(), b.len(), "{builtin}");
+        for ((qa, pa), (qb, pb)) in a.iter().zip(&b) {
+            assert_eq!(qa, qb, "{builtin}");
+            assert!(
+                (pa - pb).abs() < 1e-12,
+                "{builtin} after ZLO{}",
+                qa.as_str()
+            );
+        }
+    }
+}
+
+#[test]
+fn rerolls_avoid_repeats() {
+    let tgm: Custom = "copies=0\nhistory=4\nrerolls=4".parse().unwrap();
+    let tgm = Custom {
+        pieces: Rng::BAG.to_vec(),
+        ..tgm
+    };
+
+    let continuations = tgm.continuations(b"ZLOS", 1);
+    let total: f64 = continuations.iter().map(|x| x.1).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+
+    // a repeat needs all 5 rolls to land in the history
+    let z = continuations.iter().find(|x| x.0.get(0) == b'Z').unwrap().1;
+    assert!((z - (1.0 / 7.0) * (4.0f64 / 7.0).powi(4)).abs() < 1e-9);
+}
+
+#[test]
+fn guess_finds_the_bag_boundary() {
+    let guess = |seen: &str, len: usize| -> Vec<(String, f64)> {
+        let hint: Vec<char> = seen.chars().collect();
+        let mut out: Vec<_> = Rng::guess(&hint, len, &Builtin::Bag7)
+            .into_iter()
+            .map(|(q, p)| (q.into_iter().collect::<String>(), p))
+            .collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        out
+    };
+
+    // the repeated S ends a bag, so T and Z are all that's left of the next one
+    let after = guess("ZSSIJLO", 2);
+    assert_eq!(after.len(), 2);
+    for ((q, p), expected) in after.iter().zip(["TZ", "ZT"]) {
+        assert_eq!(q, expected);
+        assert!((p - 0.5).abs() < 1e-9);
+    }
+
+    // a second Z can only start a bag
+    let after = guess("ZZ", 1);
+    assert_eq!(
+        after.iter().map(|x| x.0.as_str()).collect::<String>(),
+        "IJLOST"
+    );
+    assert!(after.iter().all(|(_, p)| (p - 1.0 / 6.0).abs() < 1e-9));
+
+    // nothing seen, so any position in the bag is as likely
+    let after = guess("", 1);
+    assert_eq!(after.len(), 7);
+    assert!(after.iter().all(|(_, p)| (p - 1.0 / 7.0).abs() < 1e-9));
+
+    assert!(guess("ZZZ", 1).is_empty());
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use engine::{board::Board, environment::Environment, fum::tofumen, ren::best_ren, repl::Repl};
+use fumen::CellColor;
+
+#[test]
+fn path_replays_to_the_final_board() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "fth", 7, 0);
+
+    let start: Board = "XXX_|XXX_|XX__".parse().unwrap();
+    let ren = best_ren(start, Some(b'T'), b"SZIOLJ", &env).unwrap();
+    assert_eq!(ren.history.0.len(), 7);
+    assert_eq!(ren.breaks, 0);
+
+    let mut board = start;
+    for pair in &ren.history.0 {
+        let placement = pair.locate(board, &env).unwrap();
+        assert!(placement.lines > 0);
+        board = placement.board;
+    }
+    assert_eq!(board, ren.board);
+}
+
+#[test]
+fn fumen_has_a_page_per_placement() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "fth", 7, 0);
+
+    let start: Board = "XXX_|XXX_|XX__".parse().unwrap();
+    let ren = best_ren(start, Some(b'T'), b"SZIOLJ", &env).unwrap();
+    let fumen = tofumen(start, &ren.history, &env);
+    assert_eq!(fumen.pages.len(), ren.history.0.len());
+
+    let last = fumen.pages.last().unwrap();
+    assert_eq!(
+        last.comment,
+        Some(ren.history.0.last().unwrap().to_string())
+    );
+    for y in 0..23 {
+        for x in 0..10 {
+            assert_eq!(last.field[y][x] != CellColor::Empty, ren.board.get(x, y));
+        }
+    }
+}
+
+#[test]
+fn repl_reports_breaks_and_board() {
+    let mut state = common::state();
+    let mut send = |line: &str| Repl::<&[u8], Vec<u8>>::respond(&mut state, line);
+
+    assert_eq!(
+        send("ren fth - - IOJL 2"),
+        "(I:) (O:) | breaks=1 | board=_XX_|_XX_"
+    );
+    assert_eq!(send("ren fth - - IOJL 0"), "!");
+    assert!(send("ren fth XXXXX - IOJL").starts_with("err invalid "));
+    assert!(send("ren fth - TZ IOJL").ends_with("is not a valid hold"));
+    assert!(send("ren fth - X IOJL").ends_with("is not a valid hold"));
+}
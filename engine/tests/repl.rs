@@ -0,0 +1,57 @@
+mod common;
+
+use engine::repl::{Repl, State};
+
+fn send(s: &mut State, line: &str) -> String {
+    Repl::<&[u8], Vec<u8>>::respond(s, line)
+}
+
+/// The code of an `err <code> <message>` reply.
+fn code(reply: &str) -> &str {
+    let rest = reply
+        .strip_prefix("err ")
+        .unwrap_or_else(|| panic!("{reply}"));
+    rest.split(' ').next().unwrap()
+}
+
+#[test]
+fn every_error_has_its_code() {
+    let mut s = common::state();
+
+    assert_eq!(code(&send(&mut s, "pcr")), "missing");
+    assert_eq!(code(&send(&mut s, "pcr ftdhu - IOX 3")), "invalid");
+    assert_eq!(code(&send(&mut s, "pcx")), "unknown");
+    assert_eq!(code(&send(&mut s, "next")), "session");
+
+    // a table that isn't one, and one that can't be saved
+    let malformed = "data/srsx_--d--_handheld_1.pc";
+    let unwritable = "data/srsx_-t---_handheld_1.pc";
+    std::fs::write(malformed, "I = (I:\n").unwrap();
+    std::fs::create_dir(unwritable).unwrap();
+    let malformed_reply = send(&mut s, "pcp --d-- 0 1");
+    let io_reply = send(&mut s, "pcp -t--- 0 1");
+    std::fs::remove_file(malformed).unwrap();
+    std::fs::remove_dir(unwritable).unwrap();
+    assert_eq!(code(&malformed_reply), "malformed");
+    assert_eq!(code(&io_reply), "io");
+
+    // rotating needs a kick table, so this panics
+    s.kicks = "".parse().unwrap();
+    let reply = send(&mut s, "pcb ftdhu - - I 4");
+    assert_eq!(code(&reply), "internal");
+    assert!(!reply.contains('\n'));
+}
+
+#[test]
+fn panics_keep_the_session() {
+    let mut s = common::state();
+    s.pcs.insert(3, common::pcs().clone());
+    s.kicks = "".parse().unwrap();
+
+    assert_eq!(send(&mut s, "start ftdhu 3"), "");
+    assert_eq!(send(&mut s, "push II"), "");
+    assert_eq!(code(&send(&mut s, "play (I:cw)")), "internal");
+
+    assert!(s.session.is_some());
+    assert_eq!(send(&mut s, "next"), "(I:)");
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use engine::repl::{Repl, State};
+
+/// A state with an n=3 table already loaded, so nothing is read from `data`.
+fn state() -> State {
+    let mut state = common::state();
+    state.pcs.insert(3, common::pcs().clone());
+    state
+}
+
+fn send(s: &mut State, line: &str) -> String {
+    Repl::<&[u8], Vec<u8>>::respond(s, line)
+}
+
+fn planned(s: &State) -> usize {
+    s.session.as_ref().unwrap().plan.len()
+}
+
+#[test]
+fn follows_the_plan_without_replanning() {
+    let mut s = state();
+    assert_eq!(send(&mut s, "start ftdhu 3"), "");
+    assert_eq!(send(&mut s, "push OIJL"), "");
+
+    // the held O finishes the second pc
+    let first = send(&mut s, "next");
+    assert_eq!(first, "(I:)");
+    assert_eq!(planned(&s), 4);
+
+    assert_eq!(send(&mut s, "push I"), "");
+    for left in (0..4).rev() {
+        let next = send(&mut s, "next");
+        assert_eq!(send(&mut s, &format!("play {next}")), "");
+        assert_eq!(planned(&s), left);
+    }
+
+    let session = s.session.as_ref().unwrap();
+    assert!(session.board.is_empty());
+    // taking O out of hold put the last I there
+    assert_eq!(session.hold, Some(b'I'));
+    assert!(session.queue.is_empty());
+}
+
+#[test]
+fn replans_when_play_diverges() {
+    let mut s = state();
+    send(&mut s, "start ftdhu 3");
+    send(&mut s, "push OJL");
+
+    assert_eq!(planned(&s), 0);
+    let first = send(&mut s, "next");
+    assert_ne!(first, "!");
+    assert_eq!(planned(&s), 3);
+
+    // O in the middle of the board instead of where the plan has it
+    assert_eq!(send(&mut s, "play (O:)"), "");
+    assert_eq!(planned(&s), 0);
+    assert_eq!(s.session.as_ref().unwrap().queue, [b'J', b'L']);
+
+    // the rest of the pc is built around that O
+    assert_ne!(send(&mut s, "next"), "!");
+    assert_eq!(planned(&s), 2);
+    for _ in 0..2 {
+        let next = send(&mut s, "next");
+        assert_eq!(send(&mut s, &format!("play {next}")), "");
+    }
+    assert!(s.session.as_ref().unwrap().board.is_empty());
+}
+
+#[test]
+fn rejects_placements_that_cant_be_made() {
+    let mut s = state();
+    assert!(send(&mut s, "next").starts_with("err session "));
+    assert!(send(&mut s, "push I").starts_with("err session "));
+
+    send(&mut s, "start ftdu 3");
+    assert!(send(&mut s, "push IX").starts_with("err invalid "));
+    send(&mut s, "push IJ");
+
+    // no hold, so J has to wait
+    assert!(send(&mut s, "play (J:)").starts_with("err invalid "));
+    assert_eq!(send(&mut s, "play (I:)"), "");
+    assert_eq!(send(&mut s, "play (J:)"), "");
+
+    send(&mut s, "stop");
+    assert!(send(&mut s, "next").starts_with("err session "));
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use engine::{
+    environment::Environment,
+    randomizer::{Draw, Randomizer, Rng},
+    sim::{Planner, Report, Simulation},
+};
+
+/// Deals the same pieces over and over, so every game sees a known queue.
+#[derive(Clone)]
+struct Cycle(&'static [u8]);
+
+impl Randomizer for Cycle {
+    fn pieces(&self) -> &[u8] {
+        self.0
+    }
+
+    fn deal(&self, _: &mut Rng) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn next(&self, _: &Draw) -> Vec<(usize, f64, Draw)> {
+        vec![]
+    }
+
+    fn period(&self) -> usize {
+        self.0.len()
+    }
+}
+
+fn run(planner: Planner, flags: &str, cycle: &'static [u8]) -> Report {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, flags, 6, 0);
+
+    let sim = Simulation {
+        planner,
+        randomizer: Cycle(cycle),
+        seed: 42,
+        games: 2,
+        pieces: 12,
+        vision: 6,
+    };
+    sim.run(&env, common::pcs())
+}
+
+#[test]
+fn queues_that_pc_do() {
+    // every I clears the 4-wide board by itself
+    for flags in ["ftdhu", "ftdu"] {
+        let r = run(Planner::Pc, flags, b"I");
+        assert_eq!((r.pieces, r.pcs, r.failures), (24, 24, 0), "{flags}");
+    }
+
+    // JJ is a pc on its own, no hold needed
+    let r = run(Planner::Pc, "ftdu", b"JJ");
+    assert_eq!((r.pieces, r.pcs, r.failures), (24, 12, 0));
+}
+
+#[test]
+fn hold_only_with_the_flag() {
+    // O then J never pcs in order, only by holding one of them
+    let r = run(Planner::Pc, "ftdhu", b"OJ");
+    assert_eq!((r.pieces, r.pcs, r.failures), (24, 12, 0));
+
+    let r = run(Planner::Pc, "ftdu", b"OJ");
+    assert_eq!((r.pieces, r.pcs, r.failures), (0, 0, 2));
+}
+
+#[test]
+fn ren_keeps_clearing() {
+    let r = run(Planner::Ren, "ftdu", b"I");
+    assert_eq!((r.pieces, r.pcs, r.failures), (24, 24, 0));
+    assert_eq!(r.combos, [12, 12]);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use engine::{
+    board::Board,
+    environment::Environment,
+    pc::{first_pc, solve_pc},
+};
+
+fn solve(flags: &str, board: &str, hold: Option<u8>, queue: &str, height: usize) -> Vec<String> {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, flags, 0, 0);
+
+    let board: Board = board.parse().unwrap();
+    solve_pc(board, hold, queue.parse().unwrap(), height, &env)
+        .into_iter()
+        .map(|h| {
+            h.0.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[test]
+fn fills_the_gap() {
+    assert_eq!(solve("ftdhu", "XX__|XX__", None, "O", 2), ["(O:r)"]);
+    assert!(solve("ftdhu", "XX__|XX__", None, "T", 2).is_empty());
+}
+
+#[test]
+fn hold_brings_a_piece_forward() {
+    // the O goes first through hold, or after the I clears a row on top
+    assert_eq!(
+        solve("ftdhu", "XX__|XX__", None, "IO", 2),
+        ["(O:r)", "(I:) (O:r)"]
+    );
+    assert_eq!(
+        solve("ftdhu", "XX__|XX__", Some(b'O'), "I", 2),
+        ["(O:r)", "(I:) (O:r)"]
+    );
+}
+
+#[test]
+fn holdless_keeps_the_order() {
+    assert_eq!(solve("ftdu", "XX__|XX__", None, "IO", 2), ["(I:) (O:r)"]);
+    // the held O is out of reach
+    assert!(solve("ftdu", "XX__|XX__", Some(b'O'), "I", 2).is_empty());
+}
+
+#[test]
+fn every_solution_shortest_first() {
+    assert_eq!(
+        solve("ftdhu", "X___|XXX_", None, "JLT", 3),
+        ["(J:r,f)", "(J:r) (T:ccw,dr) (L:f)"]
+    );
+    assert_eq!(solve("ftdu", "X___|XXX_", None, "JLT", 3), ["(J:r,f)"]);
+}
+
+#[test]
+fn odd_minos_never_fill_rows() {
+    // 2 minos plus tetrominoes can't make whole 4-wide rows, however many there are
+    let queue = "IJLOSTZ".repeat(3) + "I";
+    assert!(solve("ftdhu", "XX__", None, &queue, 6).is_empty());
+}
+
+#[test]
+fn first_pc_uses_the_shortest_prefix() {
+    let mut state = common::state();
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+    let board: Board = "_X__|XXX_".parse().unwrap();
+    let queue = "ZSOJLITZ".parse().unwrap();
+
+    let first = first_pc(board, None, queue, 4, &env).unwrap();
+    let all = solve_pc(board, None, queue, 4, &env);
+    assert!(all.contains(&first));
+
+    // nothing with fewer pieces from the front of the queue works
+    let used = first.0.len();
+    assert!(solve_pc(board, None, queue.slice(..used - 1), 4, &env).is_empty());
+
+    assert!(first_pc(board, None, "SZ".parse().unwrap(), 4, &env).is_none());
+}
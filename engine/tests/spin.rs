@@ -0,0 +1,74 @@
+mod common;
+
+use engine::{
+    board::Board,
+    environment::Environment,
+    input::Pair,
+    repl::State,
+    spin::{Spin, SpinRule},
+};
+
+fn spin(rule: SpinRule, board: &str, pair: &str) -> (Spin, u32) {
+    let mut state = State {
+        spin_rule: rule,
+        ..common::state()
+    };
+    let env = Environment::new(&mut state, "ftdhu", 0, 0);
+
+    let board: Board = board.parse().unwrap();
+    let placement = pair.parse::<Pair>().unwrap().locate(board, &env).unwrap();
+    (placement.spin, placement.lines)
+}
+
+#[test]
+fn t_spin_double_is_full() {
+    let tsd = "X___|___X|X_XX";
+    assert_eq!(spin(SpinRule::TSpin, tsd, "(T:cw,sd,cw)"), (Spin::Full, 2));
+    assert_eq!(
+        spin(SpinRule::Handheld, tsd, "(T:cw,sd,cw)"),
+        (Spin::Full, 2)
+    );
+
+    // not rotated into place
+    assert_eq!(
+        spin(SpinRule::TSpin, "X___|____|X_XX", "(T:f,l)").0,
+        Spin::None
+    );
+}
+
+#[test]
+fn back_corners_only_make_a_mini() {
+    // pointing away from the two filled front corners
+    assert_eq!(
+        spin(SpinRule::TSpin, "X___|___X|X_XX", "(T:cw,sd,ccw)"),
+        (Spin::Mini, 1)
+    );
+    assert_eq!(
+        spin(SpinRule::TSpin, "__XX|___X|X_XX", "(T:ccw,sd,cw)"),
+        (Spin::Mini, 1)
+    );
+}
+
+#[test]
+fn tst_kick_upgrades_a_mini() {
+    // one front corner, but the last rotation took the (1, 2) kick
+    assert_eq!(
+        spin(SpinRule::TSpin, "X___|____|_XXX|___X|__XX", "(T:r,sd,l,cw)"),
+        (Spin::Full, 1)
+    );
+}
+
+#[test]
+fn all_mini_counts_immobile_pieces() {
+    let board = "XX__|X___|X_XX";
+    assert_eq!(spin(SpinRule::AllMini, board, "(S:sd,f)"), (Spin::Mini, 1));
+    assert_eq!(spin(SpinRule::TSpin, board, "(S:sd,f)"), (Spin::None, 1));
+
+    // rotated, but free to move
+    assert_eq!(spin(SpinRule::AllMini, "", "(S:cw)").0, Spin::None);
+    // T pieces still go by corners
+    assert_eq!(
+        spin(SpinRule::AllMini, "X___|___X|X_XX", "(T:cw,sd,ccw)"),
+        (Spin::Mini, 1)
+    );
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use engine::{
+    environment::Environment,
+    repl::State,
+    tbp::{BotMessage, FLAGS, FrontendMessage, Game, PC_SIZE, Tbp},
+};
+
+/// A `start` message with the columns outside the well filled `walls` rows high.
+fn start(walls: usize, width: usize, queue: &str) -> FrontendMessage {
+    start_holding(None, walls, width, queue)
+}
+
+fn start_holding(hold: Option<char>, walls: usize, width: usize, queue: &str) -> FrontendMessage {
+    let board: Vec<Vec<Option<char>>> = (0..40)
+        .map(|y| {
+            (0..width)
+                .map(|x| (y < walls && !(3..7).contains(&x)).then_some('G'))
+                .collect()
+        })
+        .collect();
+
+    serde_json::from_value(serde_json::json!({
+        "type": "start",
+        "hold": hold,
+        "queue": queue.chars().collect::<Vec<_>>(),
+        "board": board,
+    }))
+    .unwrap()
+}
+
+fn message(json: &str) -> FrontendMessage {
+    serde_json::from_str(json).unwrap()
+}
+
+fn respond(state: &mut State, game: &mut Option<Game>, m: FrontendMessage) -> Option<BotMessage> {
+    Tbp::<&[u8], Vec<u8>>::respond(state, game, m)
+}
+
+fn is_error(r: Option<BotMessage>, expected: &str) -> bool {
+    matches!(r, Some(BotMessage::Error { reason }) if reason == expected)
+}
+
+fn state() -> State {
+    let mut state = common::state();
+    state.pcs.insert(PC_SIZE, common::pcs().clone());
+    state
+}
+
+#[test]
+fn suggested_moves_are_legal_and_clear_lines() {
+    let mut state = state();
+    let mut game = None;
+
+    let ready = respond(&mut state, &mut game, message(r#"{"type":"rules"}"#));
+    assert!(matches!(ready, Some(BotMessage::Ready)));
+    let started = respond(&mut state, &mut game, start(20, 10, "IJLOSTZ"));
+    assert!(started.is_none());
+
+    for _ in 0..7 {
+        let Some(BotMessage::Suggestion { moves }) =
+            respond(&mut state, &mut game, message(r#"{"type":"suggest"}"#))
+        else {
+            panic!("no suggestion");
+        };
+        let mv = moves[0];
+
+        let board = game.as_ref().unwrap().board;
+        let env = Environment::new(&mut state, FLAGS, 0, 0);
+        assert!(mv.piece().unwrap().find(board, &env).is_some());
+
+        let play = serde_json::json!({ "type": "play", "move": mv }).to_string();
+        assert!(respond(&mut state, &mut game, message(&play)).is_none());
+    }
+
+    let game = game.unwrap();
+    assert!(game.queue.is_empty());
+    assert!(game.walls() < 20);
+    assert!(game.well().height() <= game.walls());
+}
+
+#[test]
+fn rejects_boards_it_cant_play() {
+    let mut state = state();
+    let mut game = None;
+    let mut send = |m| respond(&mut state, &mut game, m);
+
+    // nothing outside the well, so no line could ever clear
+    assert!(matches!(
+        send(start(0, 10, "IJL")),
+        Some(BotMessage::Error {
+            reason: "unsupported_board"
+        })
+    ));
+    assert!(matches!(
+        send(start(4, 11, "IJL")),
+        Some(BotMessage::Error {
+            reason: "invalid_board"
+        })
+    ));
+    assert!(send(start(4, 10, "IJL")).is_none());
+}
+
+#[test]
+fn rules_need_every_tetromino() {
+    let mut state = state();
+    state.bag = "".parse().unwrap();
+    let mut game = None;
+
+    assert!(matches!(
+        respond(&mut state, &mut game, message(r#"{"type":"rules"}"#)),
+        Some(BotMessage::Error {
+            reason: "unsupported_rules"
+        })
+    ));
+}
+
+#[test]
+fn rejects_pieces_outside_the_bag() {
+    let mut state = state();
+    let mut game = None;
+    let mut send = |m| respond(&mut state, &mut game, m);
+
+    assert!(is_error(send(start(4, 10, "IJX")), "invalid_piece"));
+    assert!(is_error(send(start(4, 10, "ijl")), "invalid_piece"));
+    assert!(is_error(
+        send(start_holding(Some('G'), 4, 10, "IJL")),
+        "invalid_piece"
+    ));
+    assert!(send(start_holding(Some('T'), 4, 10, "IJL")).is_none());
+
+    assert!(is_error(
+        send(message(r#"{"type":"new_piece","piece":"Q"}"#)),
+        "invalid_piece"
+    ));
+    assert!(send(message(r#"{"type":"new_piece","piece":"O"}"#)).is_none());
+}
+
+#[test]
+fn plays_that_dont_fit_change_nothing() {
+    let play = |x: i32, y: i32| {
+        let mv = serde_json::json!({
+            "location": { "type": "O", "orientation": "north", "x": x, "y": y },
+            "spin": "none",
+        });
+        message(&serde_json::json!({ "type": "play", "move": mv }).to_string())
+    };
+
+    let mut state = state();
+    let mut game = None;
+    assert!(respond(&mut state, &mut game, start(4, 10, "OI")).is_none());
+    let before = game.clone().unwrap();
+
+    // into the walls, off the right edge, below the floor and above the matrix
+    for (x, y) in [(0, 2), (9, 10), (4, -1), (4, 40)] {
+        let r = respond(&mut state, &mut game, play(x, y));
+        assert!(is_error(r, "invalid_move"), "({x}, {y})");
+        let g = game.as_ref().unwrap();
+        assert_eq!((g.board, &g.queue), (before.board, &before.queue));
+    }
+
+    assert!(respond(&mut state, &mut game, play(4, 0)).is_none());
+    let g = game.unwrap();
+    assert_eq!(g.well().small(), "_XX_|_XX_");
+    assert_eq!(g.queue, [b'I']);
+}
@@ -14,6 +14,7 @@ export enum FinesseStyle {
 export interface BotOptions {
   pps: number;
   vision: number;
+  foresight: number;
   n: number;
   can180: boolean;
   finesse: FinesseStyle;
@@ -23,6 +24,7 @@ export interface BotOptions {
 export const option_descriptions: Record<keyof BotOptions, string> = {
   pps: "pieces per second during [normal] pace",
   vision: "amount of pieces in the queue to consider",
+  foresight: "amount of pieces after vision to guess for when breaking ties",
   n:
     "the tallest PC we are able to perform",
   can180: "whether to do 180s",
@@ -40,6 +42,7 @@ export class Bot {
   public options: BotOptions = {
     pps: 5,
     vision: 14,
+    foresight: 0,
     n: 7,
     can180: true,
     finesse: FinesseStyle.Human,
@@ -107,16 +110,52 @@ export class Bot {
   private dead: boolean = false;
   private announced: boolean = false;
   public async regenerate(c: Engine): Promise<Array<[string, Array<Key>]>> {
-    const queue = ((c.held || '') + c.falling.symbol + c.queue.value.join('')).toUpperCase();
+    const hold = (c.held || '-').toUpperCase();
+    const queue = (c.falling.symbol + c.queue.value.join('')).toUpperCase();
 
-    const resp = await this.send(`pcr ${this.flags()} ${queue.slice(0, this.options.vision)} ${this.options.n}`);
+    // pc plans start from an empty board; anything else is combo
+    if (this.mino_count(c) !== 0) {
+      return this.combo(c, hold, queue);
+    }
+
+    const resp = await this.send(`pca ${this.flags()} ${hold} ${queue.slice(0, this.options.vision)} ${this.options.n} ${this.options.foresight}`);
+
+    if (resp === '!') {
+      return this.combo(c, hold, queue);
+    }
+
+    if (resp.startsWith('err ')) {
+      tracing.error(`engine: ${resp}`);
+      this.dead = true;
+      return [];
+    }
+
+    // `<count> | <pc> hold=<piece> | ... | rest=<queue>`; play every pc in the plan
+    const pcs = resp.split(' | ').slice(1, -1);
+    return pcs.flatMap(pc => this.placements(pc));
+  }
+
+  public async combo(c: Engine, hold: string, queue: string): Promise<Array<[string, Array<Key>]>> {
+    const resp = await this.send(`ren ${this.flags()} ${this.board(c)} ${hold} ${queue.slice(0, this.options.vision)}`);
 
     if (resp === '!') {
       this.dead = true;
       return [];
     }
-    return resp.split(' ').map(x => {
-      // console.log('part', x);
+
+    if (resp.startsWith('err ')) {
+      tracing.error(`engine: ${resp}`);
+      this.dead = true;
+      return [];
+    }
+
+    // `<placements> | breaks=<n> | board=<board>`; play the first, then look again with the next piece
+    return this.placements(resp.split(' | ')[0]).slice(0, 1);
+  }
+
+  // `(piece:keys) ...` as the engine writes them
+  public placements(s: string): Array<[string, Array<Key>]> {
+    return s.split(' ').filter(x => x.startsWith('(')).map(x => {
       let [piece, f] = x.slice(x.indexOf('(') + 1, x.indexOf(')')).split(':');
       let keys = f.split(',').filter(x => x !== '');
         keys.unshift('softDrop');
@@ -124,6 +163,17 @@ export class Bot {
     });
   }
 
+  // rows from the top, separated by `|`, the way the engine reads boards
+  public board(c: Engine): string {
+    // the bottom row comes first in the matrix
+    const rows = c.board.state.map(row => row.map(x => x === null ? '_' : 'X').join(''));
+    while (rows.length > 0 && !rows[rows.length - 1].includes('X')) {
+      rows.pop();
+    }
+
+    return rows.length === 0 ? '-' : rows.reverse().join('|');
+  }
+
   public key_presses(ks: Array<Key>, c: Engine): Array<KeyPress> {
     const keys: Array<KeyPress> = [];
     if (this.options.finesse === FinesseStyle.Human) {
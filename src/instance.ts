@@ -201,6 +201,21 @@ export class Instance {
       this.bot.options.vision = n;
     }
 
+    if (argv[0] === "foresight") {
+      const n = Number(argv[1]);
+      if (Number.isNaN(n)) {
+        return await this.room.chat("no! (not a number)");
+      }
+
+      if (n > 7 || n < 0) {
+        return await this.room.chat("no! (must be 0 <= foresight <= 7)");
+      }
+
+      await this.room.chat(`ok foresight=${n}`);
+
+      this.bot.options.foresight = n;
+    }
+
     if (argv[0] === "n") {
       const n = Number(argv[1]);
       if (Number.isNaN(n)) {